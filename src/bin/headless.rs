@@ -0,0 +1,228 @@
+use asteroids::boundary::Boundary;
+use asteroids::collision::{CollisionResponse, Contact};
+use asteroids::gravity::GravitySolver;
+use asteroids::replay::{Recording, Replay};
+use asteroids::spawn_strategy::{
+    OrbitalDiskStrategy, RandomScreenSpaceStrategy, SolarSystemStrategy, SpawnStrategy, SpawnView,
+};
+use asteroids::world::WorldState;
+use glam::Vec2;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: headless [options]
+
+Options:
+  --strategy <random|orbital|solar>  Spawn strategy (default: orbital)
+  --bodies <n>                       Number of spawn calls before the run (default: 1000)
+  --seconds <t>                      World time to simulate (default: 60)
+  --report-every <t>                 World time between stats lines (default: 1)
+  --seed <n>                         Seed for a reproducible run
+  --barnes-hut [theta]               Use the Barnes-Hut gravity solver
+  --single-threaded                  Keep the simulation on one thread
+  --boundary <mode>                  cleanup, open, toroidal or reflective (default: cleanup)
+  --box-size <s>                     Side of the box around the origin (default: 2000)
+  --collisions <mode>                merge, bounce or merge-slow (default: merge)
+  --merge-speed <v>                  Relative speed below which merge-slow merges (default: 20)
+  --replay <path>                    Play back a recording from the app, other options are ignored";
+
+struct Options {
+    strategy: Box<dyn SpawnStrategy>,
+    bodies: usize,
+    seconds: f32,
+    report_interval: f32,
+    seed: Option<u64>,
+    gravity_solver: GravitySolver,
+    parallel: bool,
+    boundary: Boundary,
+    collision_response: CollisionResponse,
+    replay: Option<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        strategy: Box::new(OrbitalDiskStrategy::new()),
+        bodies: 1000,
+        seconds: 60.0,
+        report_interval: 1.0,
+        seed: None,
+        gravity_solver: GravitySolver::BruteForce,
+        parallel: true,
+        boundary: Boundary::default(),
+        collision_response: CollisionResponse::default(),
+        replay: None,
+    };
+    let mut boundary = "cleanup".to_string();
+    let mut box_size: f32 = 2000.0;
+    let mut collisions = "merge".to_string();
+    let mut merge_speed: f32 = 20.0;
+
+    let mut args = std::env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{} expects a value", name));
+        match arg.as_str() {
+            "--strategy" => {
+                options.strategy = match value("--strategy")?.as_str() {
+                    "random" => Box::new(RandomScreenSpaceStrategy::new()),
+                    "orbital" => Box::new(OrbitalDiskStrategy::new()),
+                    "solar" => Box::new(SolarSystemStrategy::new()),
+                    other => return Err(format!("Unknown strategy: {}", other)),
+                }
+            }
+            "--bodies" => options.bodies = parse(&value("--bodies")?)?,
+            "--seconds" => options.seconds = parse(&value("--seconds")?)?,
+            "--report-every" => options.report_interval = parse(&value("--report-every")?)?,
+            "--seed" => options.seed = Some(parse(&value("--seed")?)?),
+            "--barnes-hut" => {
+                options.gravity_solver = match args.next_if(|next| !next.starts_with("--")) {
+                    Some(theta) => GravitySolver::BarnesHut {
+                        theta: parse(&theta)?,
+                    },
+                    None => GravitySolver::barnes_hut(),
+                }
+            }
+            "--single-threaded" => options.parallel = false,
+            "--boundary" => boundary = value("--boundary")?,
+            "--box-size" => box_size = parse(&value("--box-size")?)?,
+            "--collisions" => collisions = value("--collisions")?,
+            "--merge-speed" => merge_speed = parse(&value("--merge-speed")?)?,
+            "--replay" => options.replay = Some(value("--replay")?),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            other => return Err(format!("Unknown argument: {}", other)),
+        }
+    }
+
+    if options.report_interval <= 0.0 {
+        return Err("--report-every must be positive".to_string());
+    }
+    if box_size <= 0.0 {
+        return Err("--box-size must be positive".to_string());
+    }
+
+    let (center, size) = (Vec2::ZERO, Vec2::splat(box_size));
+    options.boundary = match boundary.as_str() {
+        "cleanup" => Boundary::default(),
+        "open" => Boundary::Open,
+        "toroidal" => Boundary::Toroidal { center, size },
+        "reflective" => Boundary::Reflective { center, size },
+        other => return Err(format!("Unknown boundary: {}", other)),
+    };
+    let contact = Contact::default();
+    options.collision_response = match collisions.as_str() {
+        "merge" => CollisionResponse::Merge,
+        "bounce" => CollisionResponse::Bounce(contact),
+        "merge-slow" => CollisionResponse::MergeBelow {
+            speed: merge_speed,
+            contact,
+        },
+        other => return Err(format!("Unknown collision mode: {}", other)),
+    };
+    Ok(options)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value: {}", value))
+}
+
+fn print_stats(world: &WorldState, started: Instant) {
+    let largest = world.asteroids.iter().map(|a| a.size()).fold(0.0, f32::max);
+    let total_mass: f32 = world.asteroids.iter().map(|a| a.size()).sum();
+
+    println!(
+        "t={:.1}s | asteroids: {} | largest: {:.1} | total mass: {:.1} | ejected: {:.1} | UPS: {} | wall: {:.1}s",
+        world.world_time,
+        world.asteroids.len(),
+        largest,
+        total_mass,
+        world.ejected().mass,
+        world.updates_per_second() as u32,
+        started.elapsed().as_secs_f32()
+    );
+}
+
+fn main() {
+    let mut options = match parse_args() {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(path) = &options.replay {
+        replay(path, options.report_interval);
+        return;
+    }
+
+    let mut world = match options.seed {
+        Some(seed) => WorldState::with_seed(seed),
+        None => WorldState::new(),
+    };
+    world.set_gravity_solver(options.gravity_solver);
+    world.set_parallel(options.parallel);
+    world.set_boundary(options.boundary);
+    world.set_collision_response(options.collision_response);
+    println!(
+        "Strategy: {} | Gravity: {} | Bounds: {} | Impacts: {} | Seed: {}",
+        options.strategy.name(),
+        options.gravity_solver.name(),
+        world.boundary().name(),
+        world.collision_response().name(),
+        world.seed()
+    );
+
+    let view = SpawnView::default();
+    for _ in 0..options.bodies {
+        options.strategy.spawn(&mut world, &view);
+    }
+
+    let started = Instant::now();
+    print_stats(&world, started);
+
+    // Steps never overshoot, so carry the unsimulated remainder into the next chunk
+    let reports = (options.seconds / options.report_interval).ceil() as u32;
+    let mut pending = 0.0;
+    for report in 1..=reports {
+        let target = (report as f32 * options.report_interval).min(options.seconds);
+        let previous = ((report - 1) as f32 * options.report_interval).min(options.seconds);
+        pending += target - previous;
+        pending -= world.update(pending);
+        print_stats(&world, started);
+    }
+}
+
+/// Plays a recording to the end, printing stats whenever another report interval has passed.
+fn replay(path: &str, report_interval: f32) {
+    let recording = match Recording::load(path) {
+        Ok(recording) => recording,
+        Err(error) => {
+            eprintln!("Failed to load recording from {}: {}", path, error);
+            std::process::exit(1);
+        }
+    };
+    println!("Replaying {} recorded inputs", recording.inputs.len());
+
+    let (mut world, mut strategy) = recording.start_state();
+    let mut replay = Replay::new(recording);
+    let started = Instant::now();
+    print_stats(&world, started);
+
+    let mut next_report = world.world_time + report_interval;
+    while !replay.is_finished() {
+        if let Err(desync) = replay.play_frame(&mut world, &mut strategy) {
+            eprintln!("Replay failed, {}", desync);
+            std::process::exit(1);
+        }
+        if world.world_time >= next_report {
+            print_stats(&world, started);
+            next_report = world.world_time + report_interval;
+        }
+    }
+
+    print_stats(&world, started);
+    println!("Replay finished at tick {}", world.tick());
+}
@@ -0,0 +1,134 @@
+use glam::{Vec2, vec2};
+use serde::{Deserialize, Serialize};
+
+// Images drawn per axis at most, so zooming far out over a small box stays cheap
+const MAX_IMAGES_PER_AXIS: i32 = 7;
+
+/// What happens to bodies at the edge of the world.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Boundary {
+    /// Removes asteroids further from the centre of mass than `threshold` times
+    /// the unweighted standard deviation of their distances.
+    Cleanup { threshold: f32 },
+    /// Nothing is ever removed.
+    Open,
+    /// Bodies leaving the box re-enter on the opposite side, like in classic Asteroids.
+    /// Gravity and collisions use the nearest periodic image of every other body.
+    Toroidal { center: Vec2, size: Vec2 },
+    /// Bodies bounce elastically off the box walls.
+    Reflective { center: Vec2, size: Vec2 },
+}
+
+impl Default for Boundary {
+    fn default() -> Self {
+        Boundary::Cleanup { threshold: 10.0 }
+    }
+}
+
+impl Boundary {
+    pub fn name(&self) -> &str {
+        match self {
+            Boundary::Cleanup { .. } => "Cleanup",
+            Boundary::Open => "Open",
+            Boundary::Toroidal { .. } => "Toroidal",
+            Boundary::Reflective { .. } => "Reflective",
+        }
+    }
+
+    /// Size of the periodic domain, only set for `Toroidal`.
+    pub fn period(&self) -> Option<Vec2> {
+        match *self {
+            Boundary::Toroidal { size, .. } => Some(size),
+            _ => None,
+        }
+    }
+
+    /// Offset from `from` to the nearest image of `to`.
+    pub fn separation(&self, from: Vec2, to: Vec2) -> Vec2 {
+        minimum_image(to - from, self.period())
+    }
+
+    /// The image of `to` nearest to `from`, which is `to` itself unless toroidal.
+    pub fn nearest_image(&self, from: Vec2, to: Vec2) -> Vec2 {
+        match self.period() {
+            Some(_) => from + self.separation(from, to),
+            None => to,
+        }
+    }
+
+    /// Where a point ends up once wrapped into the box. Unchanged unless toroidal.
+    pub fn wrap(&self, pos: Vec2) -> Vec2 {
+        match *self {
+            Boundary::Toroidal { center, size } => {
+                let offset = pos - center + size / 2.0;
+                center - size / 2.0 + offset - size * (offset / size).floor()
+            }
+            _ => pos,
+        }
+    }
+
+    /// Keeps a body of the given radius inside the box, wrapping or reflecting it as needed.
+    pub fn confine(&self, pos: &mut Vec2, vel: &mut Vec2, radius: f32) {
+        match *self {
+            Boundary::Toroidal { .. } => *pos = self.wrap(*pos),
+            Boundary::Reflective { center, size } => {
+                let half = (size / 2.0 - Vec2::splat(radius)).max(Vec2::ZERO);
+                let (low, high) = (center - half, center + half);
+                for axis in 0..2 {
+                    if pos[axis] < low[axis] {
+                        pos[axis] = 2.0 * low[axis] - pos[axis];
+                        vel[axis] = vel[axis].abs();
+                    } else if pos[axis] > high[axis] {
+                        pos[axis] = 2.0 * high[axis] - pos[axis];
+                        vel[axis] = -vel[axis].abs();
+                    }
+                    // Bodies moving more than a box width in one step would still be outside
+                    pos[axis] = pos[axis].clamp(low[axis], high[axis]);
+                }
+            }
+            Boundary::Cleanup { .. } | Boundary::Open => {}
+        }
+    }
+
+    /// Offsets at which periodic copies of the box overlap the rectangle `min`..`max`.
+    /// Just `Vec2::ZERO` unless toroidal.
+    pub fn image_offsets(&self, min: Vec2, max: Vec2) -> Vec<Vec2> {
+        let Boundary::Toroidal { center, size } = *self else {
+            return vec![Vec2::ZERO];
+        };
+
+        let range = |axis: usize| {
+            let tile = |x: f32| (x - center[axis]) / size[axis];
+            let middle = tile((min[axis] + max[axis]) / 2.0).round() as i32;
+            let first = (tile(min[axis]) - 0.5).floor() as i32;
+            let last = (tile(max[axis]) + 0.5).ceil() as i32;
+            first.max(middle - MAX_IMAGES_PER_AXIS / 2)..=last.min(middle + MAX_IMAGES_PER_AXIS / 2)
+        };
+
+        let mut offsets = Vec::new();
+        for j in range(1) {
+            for i in range(0) {
+                offsets.push(vec2(i as f32 * size.x, j as f32 * size.y));
+            }
+        }
+        offsets
+    }
+
+    /// Corners of the box, `None` for the unbounded modes.
+    pub fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        match *self {
+            Boundary::Toroidal { center, size } | Boundary::Reflective { center, size } => {
+                Some((center - size / 2.0, center + size / 2.0))
+            }
+            Boundary::Cleanup { .. } | Boundary::Open => None,
+        }
+    }
+}
+
+/// Shortest representative of `offset` in a periodic domain of size `period`.
+pub fn minimum_image(offset: Vec2, period: Option<Vec2>) -> Vec2 {
+    match period {
+        Some(period) => offset - period * (offset / period).round(),
+        None => offset,
+    }
+}
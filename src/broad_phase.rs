@@ -0,0 +1,246 @@
+use crate::boundary::minimum_image;
+use crate::objects::Asteroid;
+use glam::{Vec2, vec2};
+use rayon::prelude::*;
+
+// Bounds are padded slightly so rounding never drops a pair `collides_with` would accept
+const BOUNDS_MARGIN: f32 = 1e-3;
+
+/// Sweep-and-prune over the x axis.
+/// Returns every pair `(i, j)` with `i < j` whose bounding boxes overlap, sorted ascending,
+/// so callers visit pairs in the same order as a nested `i`/`j` loop would.
+/// The result doesn't depend on `parallel`.
+pub fn candidate_pairs(asteroids: &[Asteroid], parallel: bool) -> Vec<(usize, usize)> {
+    let bodies: Vec<(usize, Vec2, f32)> = asteroids
+        .iter()
+        .enumerate()
+        .map(|(i, a)| (i, a.pos(), extent(a)))
+        .collect();
+    sweep(&bodies, parallel)
+}
+
+/// Like `candidate_pairs` for asteroids in a periodic box, also pairing bodies that overlap
+/// across its edges. Positions must already be wrapped into the box.
+pub fn candidate_pairs_periodic(
+    asteroids: &[Asteroid],
+    center: Vec2,
+    size: Vec2,
+    parallel: bool,
+) -> Vec<(usize, usize)> {
+    let bodies: Vec<(usize, Vec2, f32)> = asteroids
+        .iter()
+        .enumerate()
+        .map(|(i, a)| (i, a.pos(), extent(a)))
+        .collect();
+    sweep_periodic(bodies, center, size, parallel)
+}
+
+/// Like `candidate_pairs`, with each box covering the whole path a body travelled this step.
+/// `moved[i]` is how far asteroid `i` moved to reach its current position.
+pub fn swept_pairs(asteroids: &[Asteroid], moved: &[Vec2], parallel: bool) -> Vec<(usize, usize)> {
+    sweep(&swept_bodies(asteroids, moved), parallel)
+}
+
+/// `swept_pairs` in a periodic box, see `candidate_pairs_periodic`.
+pub fn swept_pairs_periodic(
+    asteroids: &[Asteroid],
+    moved: &[Vec2],
+    center: Vec2,
+    size: Vec2,
+    parallel: bool,
+) -> Vec<(usize, usize)> {
+    sweep_periodic(swept_bodies(asteroids, moved), center, size, parallel)
+}
+
+/// Every pair `(i, j)` with `i < j` whose centres are at most `distance` apart, sorted ascending.
+/// Pairs across a periodic edge aren't found, see `pairs_within_periodic`.
+pub fn pairs_within(asteroids: &[Asteroid], distance: f32, parallel: bool) -> Vec<(usize, usize)> {
+    let mut pairs = sweep(&within_bodies(asteroids, distance), parallel);
+    pairs.retain(|&(i, j)| asteroids[i].pos().distance(asteroids[j].pos()) <= distance);
+    pairs
+}
+
+/// `pairs_within` in a periodic box, measuring between nearest images.
+/// Positions must already be wrapped into the box.
+pub fn pairs_within_periodic(
+    asteroids: &[Asteroid],
+    distance: f32,
+    center: Vec2,
+    size: Vec2,
+    parallel: bool,
+) -> Vec<(usize, usize)> {
+    let mut pairs = sweep_periodic(within_bodies(asteroids, distance), center, size, parallel);
+    pairs.retain(|&(i, j)| {
+        minimum_image(asteroids[j].pos() - asteroids[i].pos(), Some(size)).length() <= distance
+    });
+    pairs
+}
+
+/// Index of the point nearest to each of `pos`, `None` when there is no other point.
+/// With a `period` points are in a periodic box and measured between nearest images,
+/// and must already be wrapped into it. The result doesn't depend on `parallel`.
+pub fn nearest_neighbours(
+    pos: &[Vec2],
+    period: Option<Vec2>,
+    parallel: bool,
+) -> Vec<Option<usize>> {
+    let len = pos.len();
+    let mut order: Vec<usize> = (0..len).collect();
+    let by_x = |&a: &usize, &b: &usize| pos[a].x.total_cmp(&pos[b].x).then(a.cmp(&b));
+    if parallel {
+        order.par_sort_unstable_by(by_x);
+    } else {
+        order.sort_unstable_by(by_x);
+    }
+
+    // Walks outwards from position `k` of the sorted order in both directions, stopping once
+    // the gap along x alone is wider than the best distance so far
+    let order = &order;
+    let search = |k: usize| {
+        let i = order[k];
+        let mut best: Option<(f32, usize)> = None;
+        for forward in [true, false] {
+            for offset in 1..len {
+                let other = match (period, forward) {
+                    (Some(_), true) => (k + offset) % len,
+                    (Some(_), false) => (k + len - offset) % len,
+                    (None, true) if k + offset < len => k + offset,
+                    (None, false) if offset <= k => k - offset,
+                    (None, _) => break,
+                };
+                let j = order[other];
+                let gap = if forward {
+                    pos[j].x - pos[i].x
+                } else {
+                    pos[i].x - pos[j].x
+                };
+                let gap = period.map_or(gap, |period| gap.rem_euclid(period.x));
+                if best.is_some_and(|(distance, _)| gap * gap > distance) {
+                    break;
+                }
+                let distance = minimum_image(pos[j] - pos[i], period).length_squared();
+                if best.is_none_or(|(best, index)| (distance, j) < (best, index)) {
+                    best = Some((distance, j));
+                }
+            }
+        }
+        (i, best.map(|(_, j)| j))
+    };
+
+    let found: Vec<(usize, Option<usize>)> = if parallel {
+        (0..len).into_par_iter().map(search).collect()
+    } else {
+        (0..len).map(search).collect()
+    };
+    let mut nearest = vec![None; len];
+    for (i, neighbour) in found {
+        nearest[i] = neighbour;
+    }
+    nearest
+}
+
+fn within_bodies(asteroids: &[Asteroid], distance: f32) -> Vec<(usize, Vec2, f32)> {
+    asteroids
+        .iter()
+        .enumerate()
+        .map(|(i, a)| (i, a.pos(), distance / 2.0))
+        .collect()
+}
+
+fn extent(asteroid: &Asteroid) -> f32 {
+    asteroid.radius() * (1.0 + BOUNDS_MARGIN)
+}
+
+// Squares around the middle of each path, big enough to hold the body at both ends
+fn swept_bodies(asteroids: &[Asteroid], moved: &[Vec2]) -> Vec<(usize, Vec2, f32)> {
+    asteroids
+        .iter()
+        .zip(moved)
+        .enumerate()
+        .map(|(i, (a, &moved))| (i, a.pos() - moved / 2.0, extent(a) + moved.length() / 2.0))
+        .collect()
+}
+
+fn sweep_periodic(
+    entries: Vec<(usize, Vec2, f32)>,
+    center: Vec2,
+    size: Vec2,
+    parallel: bool,
+) -> Vec<(usize, usize)> {
+    // Bodies this close to an edge can touch one on the other side, so they get a shifted copy
+    let reach = 2.0 * entries.iter().map(|entry| entry.2).fold(0.0, f32::max);
+    let low = center - size / 2.0 + Vec2::splat(reach);
+    let high = center + size / 2.0 - Vec2::splat(reach);
+    let shift = |pos: Vec2, axis: usize| {
+        if pos[axis] > high[axis] {
+            -size[axis]
+        } else if pos[axis] < low[axis] {
+            size[axis]
+        } else {
+            0.0
+        }
+    };
+
+    let mut bodies = Vec::with_capacity(entries.len());
+    for (i, pos, body_extent) in entries {
+        let (dx, dy) = (shift(pos, 0), shift(pos, 1));
+        bodies.push((i, pos, body_extent));
+        if dx != 0.0 {
+            bodies.push((i, pos + vec2(dx, 0.0), body_extent));
+        }
+        if dy != 0.0 {
+            bodies.push((i, pos + vec2(0.0, dy), body_extent));
+        }
+        if dx != 0.0 && dy != 0.0 {
+            bodies.push((i, pos + vec2(dx, dy), body_extent));
+        }
+    }
+
+    let mut pairs = sweep(&bodies, parallel);
+    // A pair straddling an edge is found through the copies of both bodies
+    pairs.dedup();
+    pairs
+}
+
+/// Pairs of overlapping `(index, position, extent)` entries, reported by index.
+fn sweep(bodies: &[(usize, Vec2, f32)], parallel: bool) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..bodies.len()).collect();
+    let by_min_x = |&a: &usize, &b: &usize| {
+        let min_a = bodies[a].1.x - bodies[a].2;
+        let min_b = bodies[b].1.x - bodies[b].2;
+        min_a.total_cmp(&min_b).then(a.cmp(&b))
+    };
+
+    if parallel {
+        order.par_sort_unstable_by(by_min_x);
+    } else {
+        order.sort_unstable_by(by_min_x);
+    }
+
+    // Pairs starting at position `k` of the sorted order
+    let order = &order;
+    let sweep = |k: usize| {
+        let (i, pos_i, extent_i) = bodies[order[k]];
+        let max_x = pos_i.x + extent_i;
+
+        order[k + 1..]
+            .iter()
+            .map(move |&other| bodies[other])
+            .take_while(move |&(_, pos, extent)| pos.x - extent <= max_x)
+            .filter(move |&(j, pos, extent)| j != i && (pos_i.y - pos.y).abs() <= extent_i + extent)
+            .map(move |(j, _, _)| (i.min(j), i.max(j)))
+    };
+
+    let mut pairs: Vec<(usize, usize)>;
+    if parallel {
+        pairs = (0..order.len())
+            .into_par_iter()
+            .flat_map_iter(sweep)
+            .collect();
+        pairs.par_sort_unstable();
+    } else {
+        pairs = (0..order.len()).flat_map(sweep).collect();
+        pairs.sort_unstable();
+    }
+    pairs
+}
@@ -0,0 +1,167 @@
+use crate::boundary::Boundary;
+use crate::broad_phase;
+use crate::orbit;
+use crate::world::WorldState;
+use glam::Vec2;
+
+/// How `WorldState::groups` partitions the asteroids.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Clustering {
+    /// Bodies closer than `linking_length` share a group, and so do their friends.
+    /// In a toroidal world pairs are linked across the edges too.
+    FriendsOfFriends { linking_length: f32 },
+    /// Every body that orbits the heaviest one directly forms a group with everything
+    /// inside its Hill sphere, such as a planet with its moons. The heaviest body is a
+    /// group on its own. See `orbit::hill_hierarchy`.
+    #[default]
+    HillSpheres,
+}
+
+impl Clustering {
+    pub fn name(&self) -> &str {
+        match self {
+            Clustering::FriendsOfFriends { .. } => "Friends-of-friends",
+            Clustering::HillSpheres => "Hill spheres",
+        }
+    }
+}
+
+/// A set of asteroids moving together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    /// Member IDs in ascending order.
+    pub members: Vec<u64>,
+    /// ID of the heaviest member.
+    pub primary: u64,
+    pub mass: f32,
+    pub barycenter: Vec2,
+    pub velocity: Vec2,
+}
+
+impl Group {
+    pub fn contains(&self, id: u64) -> bool {
+        self.members.binary_search(&id).is_ok()
+    }
+
+    /// The same members as they are in `world` now, without searching for groups again.
+    /// Members that have since merged or been removed drop out, `None` once none are left.
+    pub fn refreshed(&self, world: &WorldState) -> Option<Group> {
+        let indices: Vec<usize> = (0..world.asteroids.len())
+            .filter(|&i| self.contains(world.asteroids[i].id()))
+            .collect();
+        (!indices.is_empty()).then(|| summarize(world, &indices))
+    }
+}
+
+/// Partitions the asteroids of `world`, heaviest group first.
+pub fn find_groups(world: &WorldState, clustering: Clustering) -> Vec<Group> {
+    let asteroids = &world.asteroids;
+    let mut groups = UnionFind::new(asteroids.len());
+
+    match clustering {
+        Clustering::FriendsOfFriends { linking_length } => {
+            let pairs = match world.boundary() {
+                Boundary::Toroidal { center, size } => broad_phase::pairs_within_periodic(
+                    asteroids,
+                    linking_length,
+                    center,
+                    size,
+                    world.parallel(),
+                ),
+                _ => broad_phase::pairs_within(asteroids, linking_length, world.parallel()),
+            };
+            for (i, j) in pairs {
+                groups.union(i, j);
+            }
+        }
+        Clustering::HillSpheres => {
+            let hierarchy = orbit::hill_hierarchy(world);
+            let index_of = |sphere: usize| hierarchy[sphere].index;
+            // Primaries come first, so joining each body to its primary covers whole subtrees
+            for (sphere, entry) in hierarchy.iter().enumerate() {
+                if let Some(primary) = entry.primary
+                    && hierarchy[primary].primary.is_some()
+                {
+                    groups.union(index_of(sphere), index_of(primary));
+                }
+            }
+        }
+    }
+
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); asteroids.len()];
+    for i in 0..asteroids.len() {
+        members[groups.find(i)].push(i);
+    }
+
+    let mut result: Vec<Group> = members
+        .into_iter()
+        .filter(|indices| !indices.is_empty())
+        .map(|indices| summarize(world, &indices))
+        .collect();
+
+    result.sort_by(|a, b| b.mass.total_cmp(&a.mass).then(a.primary.cmp(&b.primary)));
+    result
+}
+
+// Group made of the asteroids at `indices`, which mustn't be empty
+fn summarize(world: &WorldState, indices: &[usize]) -> Group {
+    let asteroids = &world.asteroids;
+    let boundary = world.boundary();
+    let primary = indices
+        .iter()
+        .map(|&i| &asteroids[i])
+        .max_by(|a, b| a.size().total_cmp(&b.size()).then(b.id().cmp(&a.id())))
+        .unwrap();
+
+    // Positions relative to the primary, so groups straddling a periodic edge stay whole
+    let mut mass = 0.0;
+    let mut weighted_offset = Vec2::ZERO;
+    let mut momentum = Vec2::ZERO;
+    for &i in indices {
+        let asteroid = &asteroids[i];
+        mass += asteroid.size();
+        weighted_offset += boundary.separation(primary.pos(), asteroid.pos()) * asteroid.size();
+        momentum += asteroid.vel() * asteroid.size();
+    }
+
+    let mut ids: Vec<u64> = indices.iter().map(|&i| asteroids[i].id()).collect();
+    ids.sort_unstable();
+    Group {
+        members: ids,
+        primary: primary.id(),
+        mass,
+        barycenter: boundary.wrap(primary.pos() + weighted_offset / mass),
+        velocity: momentum / mass,
+    }
+}
+
+// Disjoint sets over indices with path halving
+pub(crate) struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    pub(crate) fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    pub(crate) fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    pub(crate) fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        // The lower index becomes the root, which keeps the result independent of call order
+        if a < b {
+            self.parent[b] = a;
+        } else {
+            self.parent[a] = b;
+        }
+    }
+}
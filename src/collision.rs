@@ -0,0 +1,130 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Restitution and friction of a bouncing contact, shared by ship impacts and asteroid bounces.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Contact {
+    /// Share of the approach speed returned along the contact normal, 0 to 1.
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+impl Default for Contact {
+    fn default() -> Self {
+        Self {
+            restitution: 0.5,
+            friction: 0.5,
+        }
+    }
+}
+
+impl Contact {
+    /// Impulse the second of two touching bodies receives at the contact point, the first gets
+    /// the opposite. `normal` is the unit direction from the first body to the second,
+    /// `distance` the gap between their centres and `relative_vel` the velocity of the second
+    /// body's surface minus the first's at the contact point, spin included.
+    /// `rotational_masses` are each body's moment of inertia over its squared distance from
+    /// centre to contact point, infinite for bodies that don't turn.
+    ///
+    /// The normal part uses the reduced mass and only acts while the bodies approach.
+    /// Friction acts against the sliding over `dt`, pressing harder the closer their centres,
+    /// and never more than it takes to stop the sliding.
+    pub fn impulse(
+        &self,
+        normal: Vec2,
+        distance: f32,
+        relative_vel: Vec2,
+        masses: [f32; 2],
+        rotational_masses: [f32; 2],
+        dt: f32,
+    ) -> Vec2 {
+        let reduced_mass = masses[0] * masses[1] / (masses[0] + masses[1]);
+        // Sliding is resisted by the bodies turning as well as moving
+        let sliding_mass = 1.0
+            / (1.0 / masses[0]
+                + 1.0 / masses[1]
+                + 1.0 / rotational_masses[0]
+                + 1.0 / rotational_masses[1]);
+
+        let approach_speed = relative_vel.dot(normal).min(0.0);
+        let normal_impulse = -(1.0 + self.restitution) * approach_speed * reduced_mass;
+
+        let tangent = normal.perp();
+        let sliding = relative_vel.dot(tangent);
+        let normal_force = masses[0] * masses[1] / distance;
+        let friction =
+            (self.friction * normal_force * relative_vel.normalize_or_zero().dot(tangent) * dt)
+                .clamp(-sliding.abs() * sliding_mass, sliding.abs() * sliding_mass);
+
+        normal * normal_impulse - tangent * friction
+    }
+}
+
+/// How far two overlapping bodies move to just touch, the lighter one moving further.
+/// `normal` points from the first body to the second.
+pub fn separation(normal: Vec2, overlap: f32, masses: [f32; 2]) -> [Vec2; 2] {
+    if overlap <= 0.0 {
+        return [Vec2::ZERO; 2];
+    }
+    let total_mass = masses[0] + masses[1];
+    [
+        -normal * overlap * masses[1] / total_mass,
+        normal * overlap * masses[0] / total_mass,
+    ]
+}
+
+/// Fraction of a step, 0 to 1, at which two circles moving in straight lines first touch,
+/// `None` if they stay apart. `offset` is the second centre minus the first at the end of the
+/// step, `displacement` how far the second moved relative to the first during it, and `radius`
+/// the sum of their radii. Circles that already touch at the start give 0.
+pub fn time_of_impact(offset: Vec2, displacement: Vec2, radius: f32) -> Option<f32> {
+    let start = offset - displacement;
+    let c = start.length_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let a = displacement.length_squared();
+    let b = 2.0 * start.dot(displacement);
+    let discriminant = b * b - 4.0 * a * c;
+    if a == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let time = (-b - discriminant.sqrt()) / (2.0 * a);
+    (0.0..=1.0).contains(&time).then_some(time)
+}
+
+/// What happens when two asteroids touch. Pairs that merge can still shatter when the world
+/// has fragmentation enabled, and pinned or kinematic bodies always absorb what they hit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CollisionResponse {
+    #[default]
+    Merge,
+    Bounce(Contact),
+    /// Merges pairs slower than `speed` relative to each other and bounces the rest,
+    /// so gentle contacts accrete into rubble piles.
+    MergeBelow {
+        speed: f32,
+        contact: Contact,
+    },
+}
+
+impl CollisionResponse {
+    pub fn name(&self) -> &str {
+        match self {
+            CollisionResponse::Merge => "Merge",
+            CollisionResponse::Bounce(_) => "Bounce",
+            CollisionResponse::MergeBelow { .. } => "Merge slow",
+        }
+    }
+
+    /// The contact to bounce with at `relative_speed`, `None` when the bodies merge.
+    pub fn bounce(&self, relative_speed: f32) -> Option<Contact> {
+        match *self {
+            CollisionResponse::Merge => None,
+            CollisionResponse::Bounce(contact) => Some(contact),
+            CollisionResponse::MergeBelow { speed, contact } => {
+                (relative_speed >= speed).then_some(contact)
+            }
+        }
+    }
+}
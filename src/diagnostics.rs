@@ -0,0 +1,122 @@
+use crate::boundary::minimum_image;
+use crate::gravity::Gravity;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Conserved quantities of a set of bodies.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Conservation {
+    pub kinetic_energy: f32,
+    /// Pairwise potential energy under the active force law.
+    /// Only differences are meaningful, the zero point depends on the law.
+    pub potential_energy: f32,
+    pub momentum: Vec2,
+    /// Angular momentum about the centre of mass, counter-clockwise positive.
+    pub angular_momentum: f32,
+    /// Everything removed from the simulation so far, filled in by `WorldState::conservation`.
+    pub ejected: Ejected,
+}
+
+/// Change of the conserved quantities relative to a baseline.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ConservationDrift {
+    /// Change in total energy, relative to the baseline magnitude when it is non-zero.
+    pub energy: f32,
+    pub momentum: Vec2,
+    /// Change in angular momentum, relative to the baseline magnitude when it is non-zero.
+    pub angular_momentum: f32,
+    /// Mass removed since the baseline.
+    pub ejected_mass: f32,
+    /// Momentum carried away by bodies removed since the baseline.
+    pub ejected_momentum: Vec2,
+}
+
+impl ConservationDrift {
+    /// Momentum change not explained by ejections, which points at the physics itself.
+    pub fn unexplained_momentum(&self) -> Vec2 {
+        self.momentum + self.ejected_momentum
+    }
+}
+
+/// An asteroid removed by the cleanup boundary.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Ejection {
+    pub id: u64,
+    pub mass: f32,
+    pub momentum: Vec2,
+    pub pos: Vec2,
+    pub world_time: f32,
+}
+
+/// Running totals over every ejection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Ejected {
+    pub count: usize,
+    pub mass: f32,
+    pub momentum: Vec2,
+}
+
+impl Ejected {
+    pub fn add(&mut self, ejection: &Ejection) {
+        self.count += 1;
+        self.mass += ejection.mass;
+        self.momentum += ejection.momentum;
+    }
+}
+
+impl Conservation {
+    /// Measures bodies given as `(position, velocity, mass)`. Potential energy is an O(n²) sum,
+    /// over nearest periodic images when `period` is set.
+    pub fn measure(bodies: &[(Vec2, Vec2, f32)], gravity: &Gravity, period: Option<Vec2>) -> Self {
+        let total_mass: f32 = bodies.iter().map(|(_, _, m)| m).sum();
+        if total_mass <= 0.0 {
+            return Self::default();
+        }
+
+        let mut result = Self::default();
+        let mut weighted_pos = Vec2::ZERO;
+        for &(pos, vel, mass) in bodies {
+            result.kinetic_energy += 0.5 * mass * vel.length_squared();
+            result.momentum += vel * mass;
+            weighted_pos += pos * mass;
+        }
+        let center = weighted_pos / total_mass;
+        let center_vel = result.momentum / total_mass;
+
+        // The pair sum has many terms of mixed sign, accumulate it in double precision
+        let mut potential_energy = 0.0f64;
+        for (i, &(pos, vel, mass)) in bodies.iter().enumerate() {
+            result.angular_momentum += mass * (pos - center).perp_dot(vel - center_vel);
+
+            for &(other_pos, _, other_mass) in &bodies[i + 1..] {
+                let distance = minimum_image(other_pos - pos, period).length();
+                potential_energy += (mass * gravity.potential(other_mass, distance)) as f64;
+            }
+        }
+        result.potential_energy = potential_energy as f32;
+
+        result
+    }
+
+    pub fn total_energy(&self) -> f32 {
+        self.kinetic_energy + self.potential_energy
+    }
+
+    pub fn drift_from(&self, baseline: &Conservation) -> ConservationDrift {
+        let relative = |now: f32, then: f32| {
+            if then != 0.0 {
+                (now - then) / then.abs()
+            } else {
+                now - then
+            }
+        };
+
+        ConservationDrift {
+            energy: relative(self.total_energy(), baseline.total_energy()),
+            momentum: self.momentum - baseline.momentum,
+            angular_momentum: relative(self.angular_momentum, baseline.angular_momentum),
+            ejected_mass: self.ejected.mass - baseline.ejected.mass,
+            ejected_momentum: self.ejected.momentum - baseline.ejected.momentum,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use crate::diagnostics::Ejection;
+use glam::Vec2;
+
+/// Something that happened in the world, see `EventKind`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// World tick the event happened in, events between updates carry the next tick.
+    pub tick: u64,
+    pub world_time: f32,
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    /// Two or more overlapping asteroids merged into `id`, parents in ascending order.
+    Merged {
+        id: u64,
+        parents: Vec<u64>,
+        mass: f32,
+        pos: Vec2,
+    },
+    /// Two asteroids broke up into `fragments`.
+    Fragmented {
+        parents: [u64; 2],
+        fragments: Vec<u64>,
+    },
+    /// Two asteroids bounced off each other, `impulse` is what the second one received.
+    Bounced {
+        bodies: [u64; 2],
+        impulse: Vec2,
+    },
+    /// An asteroid was dropped by the cleanup boundary.
+    Removed(Ejection),
+    /// The ship touched an asteroid. `impulse` is what the asteroid received,
+    /// `damage` the health the ship lost.
+    ShipCollided {
+        asteroid: u64,
+        impulse: Vec2,
+        damage: f32,
+    },
+    ShipDied {
+        pos: Vec2,
+    },
+    ShipRespawned {
+        pos: Vec2,
+        vel: Vec2,
+    },
+    /// A body was added through `WorldState::spawn`, usually by a spawn strategy.
+    Spawned {
+        id: u64,
+        pos: Vec2,
+        vel: Vec2,
+        mass: f32,
+    },
+}
+
+pub type Subscriber = Box<dyn FnMut(&Event) + Send>;
+
+/// Delivers events to subscribers as they happen, and queues them for draining when enabled.
+/// Events are dropped when nobody is listening.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Subscriber>,
+    queue: Option<Vec<Event>>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, subscriber: impl FnMut(&Event) + Send + 'static) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    /// Starts or stops queueing events for `drain`, dropping anything still queued when stopped.
+    pub fn set_queued(&mut self, queued: bool) {
+        self.queue = queued.then(Vec::new);
+    }
+
+    /// Events since the last call, oldest first. Empty unless queueing is enabled.
+    pub fn drain(&mut self) -> Vec<Event> {
+        self.queue.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    pub fn is_listening(&self) -> bool {
+        self.queue.is_some() || !self.subscribers.is_empty()
+    }
+
+    pub fn emit(&mut self, event: Event) {
+        for subscriber in &mut self.subscribers {
+            subscriber(&event);
+        }
+        if let Some(queue) = &mut self.queue {
+            queue.push(event);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use crate::objects::Asteroid;
+use crate::spawn_strategy::power_law_sample;
+use glam::{Vec2, vec2};
+use serde::{Deserialize, Serialize};
+
+/// When an impact is violent enough to shatter the bodies instead of merging them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FragmentationThreshold {
+    /// Relative speed of the two bodies at contact.
+    RelativeSpeed(f32),
+    /// Impact energy per unit of total mass: 0.5 * reduced_mass * v_rel^2 / total_mass.
+    SpecificEnergy(f32),
+}
+
+/// Fragmentation model for high-energy asteroid impacts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Fragmentation {
+    pub threshold: FragmentationThreshold,
+    /// Smallest fragment, as a fraction of the combined mass of the colliding bodies.
+    pub min_fragment_fraction: f32,
+    /// Smallest fragment in absolute terms. Collisions that can't produce two of these merge instead.
+    pub min_fragment_size: f32,
+    /// Power-law exponent for fragment masses, lower values give more uneven splits.
+    pub size_alpha: f32,
+    pub max_fragments: usize,
+    /// Share of the impact energy that goes into scattering the fragments, the rest is dissipated.
+    pub energy_retention: f32,
+}
+
+impl Default for Fragmentation {
+    fn default() -> Self {
+        Self {
+            threshold: FragmentationThreshold::RelativeSpeed(100.0),
+            min_fragment_fraction: 0.05,
+            min_fragment_size: 1.0,
+            size_alpha: 1.5,
+            max_fragments: 8,
+            energy_retention: 0.3,
+        }
+    }
+}
+
+impl Fragmentation {
+    pub fn shatters(&self, a: &Asteroid, b: &Asteroid) -> bool {
+        let total_mass = a.size() + b.size();
+        if total_mass < 2.0 * self.min_fragment_size {
+            return false;
+        }
+
+        let relative_speed = (a.vel() - b.vel()).length();
+        match self.threshold {
+            FragmentationThreshold::RelativeSpeed(speed) => relative_speed > speed,
+            FragmentationThreshold::SpecificEnergy(energy) => {
+                let reduced_mass = a.size() * b.size() / total_mass;
+                0.5 * reduced_mass * relative_speed * relative_speed / total_mass > energy
+            }
+        }
+    }
+
+    /// Breaks two colliding asteroids into fragments.
+    /// Total mass, the centre of mass, linear momentum and angular momentum all match the colliding pair.
+    pub fn fragment(&self, a: &Asteroid, b: &Asteroid, rng: &mut fastrand::Rng) -> Vec<Asteroid> {
+        let total_mass = a.size() + b.size();
+        let center = (a.pos() * a.size() + b.pos() * b.size()) / total_mass;
+        let center_vel = (a.vel() * a.size() + b.vel() * b.size()) / total_mass;
+
+        let masses = self.fragment_masses(total_mass, rng);
+        if masses.len() < 2 {
+            return vec![a.merge_with(b)];
+        }
+        // Every fragment is made of the mixed material of the pair
+        let merged = a.merge_with(b);
+        let composition = merged.composition();
+        let fragment = |pos: Vec2, vel: Vec2, mass: f32| {
+            Asteroid::new(pos, vel, mass).with_composition(composition)
+        };
+
+        // Lay fragments out on a ring, each taking an arc proportional to its diameter,
+        // which keeps neighbours from touching
+        let diameters: Vec<f32> = masses
+            .iter()
+            .map(|&m| fragment(center, center_vel, m).radius() * 2.0)
+            .collect();
+        let total_diameter: f32 = diameters.iter().sum();
+        let ring_radius = total_diameter / 3.0;
+        let start_angle = rng.f32() * 2.0 * std::f32::consts::PI;
+
+        let mut arc = 0.0;
+        let directions: Vec<Vec2> = diameters
+            .iter()
+            .map(|d| {
+                let angle =
+                    start_angle + 2.0 * std::f32::consts::PI * (arc + d / 2.0) / total_diameter;
+                arc += d;
+                vec2(angle.cos(), angle.sin())
+            })
+            .collect();
+
+        // Remove the mass-weighted mean offset and drift so the fragments share the pair's
+        // centre of mass and momentum
+        let mut offset_mean = vec2(0.0, 0.0);
+        let mut drift_mean = vec2(0.0, 0.0);
+        let mut drifts = Vec::with_capacity(masses.len());
+        for (direction, &mass) in directions.iter().zip(&masses) {
+            let drift = *direction * (0.5 + rng.f32());
+            offset_mean += *direction * ring_radius * mass;
+            drift_mean += drift * mass;
+            drifts.push(drift);
+        }
+        offset_mean /= total_mass;
+        drift_mean /= total_mass;
+        for drift in &mut drifts {
+            *drift -= drift_mean;
+        }
+
+        // Scale the scatter velocities to carry the retained share of the impact energy
+        let relative_speed = (a.vel() - b.vel()).length();
+        let reduced_mass = a.size() * b.size() / total_mass;
+        let impact_energy = 0.5 * reduced_mass * relative_speed * relative_speed;
+        let drift_energy: f32 = drifts
+            .iter()
+            .zip(&masses)
+            .map(|(drift, &mass)| 0.5 * mass * drift.length_squared())
+            .sum();
+        let scale = if drift_energy > 0.0 {
+            (self.energy_retention * impact_energy / drift_energy).sqrt()
+        } else {
+            0.0
+        };
+
+        let mut fragments: Vec<Asteroid> = directions
+            .iter()
+            .zip(&drifts)
+            .zip(&masses)
+            .map(|((direction, drift), &mass)| {
+                let pos = center + *direction * ring_radius - offset_mean;
+                fragment(pos, center_vel + *drift * scale, mass)
+            })
+            .collect();
+
+        // The merged body spins with the pair's spins and orbit about their centre of mass.
+        // Whatever the fragments' own motion about that centre doesn't carry goes into a
+        // common spin
+        let mut angular_momentum = merged.spin_angular_momentum();
+        let mut inertia = 0.0;
+        for body in &fragments {
+            angular_momentum -=
+                body.size() * (body.pos() - merged.pos()).perp_dot(body.vel() - merged.vel());
+            inertia += body.moment_of_inertia();
+        }
+        for body in &mut fragments {
+            body.set_spin(angular_momentum / inertia);
+        }
+        fragments
+    }
+
+    /// Power-law distributed masses summing to `total_mass`.
+    fn fragment_masses(&self, total_mass: f32, rng: &mut fastrand::Rng) -> Vec<f32> {
+        let min_mass = (total_mass * self.min_fragment_fraction).max(self.min_fragment_size);
+        let mut masses = Vec::new();
+        let mut remaining = total_mass;
+
+        while remaining > 0.0 {
+            let mass = power_law_sample(rng, min_mass, self.size_alpha);
+            if mass >= remaining - min_mass || masses.len() + 1 >= self.max_fragments {
+                masses.push(remaining);
+                break;
+            }
+            masses.push(mass);
+            remaining -= mass;
+        }
+
+        masses
+    }
+}
@@ -1,7 +1,8 @@
 use crate::color::Color;
+use crate::spawn_strategy::SpawnView;
 use fontdue::layout::{CoordinateSystem, Layout, LayoutSettings, TextStyle};
 use fontdue::{Font, FontSettings};
-use glam::{vec2, Vec2};
+use glam::{Vec2, vec2};
 use image::RgbaImage;
 use pixels::Pixels;
 use std::collections::HashSet;
@@ -13,6 +14,8 @@ const CAMERA_SPEED: f32 = 300.0;
 pub enum CameraMode {
     Manual,
     TrackingCenterOfMass,
+    /// Follows the barycentre of the group containing this asteroid.
+    TrackingGroup(u64),
     ShipControl,
 }
 
@@ -21,6 +24,7 @@ impl CameraMode {
         match self {
             CameraMode::Manual => "Manual",
             CameraMode::TrackingCenterOfMass => "Tracking",
+            CameraMode::TrackingGroup(_) => "Group",
             CameraMode::ShipControl => "Ship",
         }
     }
@@ -78,6 +82,18 @@ impl FrameBuffer {
         }
     }
 
+    /// `time_scale` is the simulation speed, see `SpawnView::time_scale`.
+    pub fn spawn_view(&self, time_scale: f32) -> SpawnView {
+        SpawnView {
+            camera_pos: self.camera_pos,
+            camera_vel: self.camera_vel,
+            zoom: self.zoom,
+            width: self.width as f32,
+            height: self.height as f32,
+            time_scale,
+        }
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -132,8 +148,7 @@ impl FrameBuffer {
     }
 
     pub fn draw_circle(&mut self, world_pos: Vec2, world_radius: f32, color: Color) {
-        let screen_center = vec2(self.width as f32 / 2.0, self.height as f32 / 2.0);
-        let screen_pos = (world_pos - self.camera_pos) * self.zoom + screen_center;
+        let screen_pos = self.world_to_screen(world_pos);
         let screen_radius = world_radius * self.zoom;
 
         // For very small asteroids, just draw a single dimmed pixel
@@ -264,6 +279,11 @@ impl FrameBuffer {
         }
     }
 
+    pub fn world_to_screen(&self, world_pos: Vec2) -> Vec2 {
+        let screen_center = vec2(self.width as f32 / 2.0, self.height as f32 / 2.0);
+        (world_pos - self.camera_pos) * self.zoom + screen_center
+    }
+
     pub fn screen_to_world(&self, screen_pos: Vec2) -> Vec2 {
         let screen_center = vec2(self.width as f32 / 2.0, self.height as f32 / 2.0);
         (screen_pos - screen_center) / self.zoom + self.camera_pos
@@ -309,7 +329,7 @@ impl FrameBuffer {
     pub fn finish_creating_asteroid(
         &mut self,
         screen_pos: Vec2,
-        actual_speed: f32,
+        time_scale: f32,
     ) -> (Vec2, Vec2, f32) {
         let screen_center = vec2(self.width as f32 / 2.0, self.height as f32 / 2.0);
 
@@ -322,9 +342,9 @@ impl FrameBuffer {
         // Convert velocity from screen to world
         let mut world_vel = self.asteroid_screen_vel / self.zoom + self.camera_vel;
 
-        // Divide by actual_speed so faster simulation = smaller velocity in world units
-        if actual_speed > 0.0 {
-            world_vel /= actual_speed;
+        // Divide by the time scale so faster simulation = smaller velocity in world units
+        if time_scale > 0.0 {
+            world_vel /= time_scale;
         }
 
         let size = self.asteroid_size;
@@ -355,7 +375,7 @@ impl FrameBuffer {
             self.camera_vel.x += speed;
         }
 
-        self.camera_pos = self.camera_pos + self.camera_vel * dt;
+        self.camera_pos += self.camera_vel * dt;
     }
 
     pub fn draw_screen_line(&mut self, p0: Vec2, p1: Vec2, color: Color) {
@@ -377,6 +397,11 @@ impl FrameBuffer {
         }
     }
 
+    pub fn draw_world_line(&mut self, p0: Vec2, p1: Vec2, color: Color) {
+        let (p0, p1) = (self.world_to_screen(p0), self.world_to_screen(p1));
+        self.draw_screen_line(p0, p1, color);
+    }
+
     pub fn draw_screen_triangle(&mut self, p0: Vec2, p1: Vec2, p2: Vec2, color: Color) {
         // Find bounding box
         let min_x = p0.x.min(p1.x).min(p2.x).floor() as i32;
@@ -429,8 +454,7 @@ impl FrameBuffer {
         scale: f32,
         orientation: f32,
     ) {
-        let screen_center = vec2(self.width as f32 / 2.0, self.height as f32 / 2.0);
-        let screen_pos = (world_pos - self.camera_pos) * self.zoom + screen_center;
+        let screen_pos = self.world_to_screen(world_pos);
 
         let (sprite_width, sprite_height) = sprite.dimensions();
         let scaled_size = scale * self.zoom;
@@ -0,0 +1,161 @@
+use crate::objects::Asteroid;
+use crate::quadtree::QuadTree;
+use glam::Vec2;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_OPENING_ANGLE: f32 = 0.5;
+
+/// Shape of the attraction between two bodies.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ForceLaw {
+    /// 2D gravity from a logarithmic potential, F = G * M / r.
+    /// Circular orbit speed doesn't depend on the radius.
+    #[default]
+    Logarithmic,
+    /// Newtonian inverse-square law, F = G * M / r^2.
+    Newtonian,
+}
+
+impl ForceLaw {
+    pub fn name(&self) -> &str {
+        match self {
+            ForceLaw::Logarithmic => "1/r",
+            ForceLaw::Newtonian => "1/r²",
+        }
+    }
+
+    /// Power of the distance the force falls off with.
+    pub fn exponent(&self) -> f32 {
+        match self {
+            ForceLaw::Logarithmic => 1.0,
+            ForceLaw::Newtonian => 2.0,
+        }
+    }
+}
+
+/// Force law together with its constants.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Gravity {
+    pub law: ForceLaw,
+    pub constant: f32,
+    /// Plummer softening length, replaces r^2 with r^2 + softening^2.
+    /// While zero, overlapping bodies don't attract each other at all.
+    pub softening: f32,
+}
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Self {
+            law: ForceLaw::default(),
+            constant: 1.0,
+            softening: 0.0,
+        }
+    }
+}
+
+impl Gravity {
+    /// Acceleration towards a point mass `mass` located at `direction` from the body.
+    pub fn acceleration(&self, direction: Vec2, mass: f32) -> Vec2 {
+        let softened = direction.length_squared() + self.softening * self.softening;
+        if softened <= 0.0 {
+            return Vec2::ZERO;
+        }
+        let scale = match self.law {
+            ForceLaw::Logarithmic => 1.0 / softened,
+            ForceLaw::Newtonian => 1.0 / (softened * softened.sqrt()),
+        };
+        direction * (self.constant * mass * scale)
+    }
+
+    /// Potential energy per unit mass at `distance` from a point mass `mass`.
+    pub fn potential(&self, mass: f32, distance: f32) -> f32 {
+        let softened = distance * distance + self.softening * self.softening;
+        if softened <= 0.0 {
+            return 0.0;
+        }
+        match self.law {
+            ForceLaw::Logarithmic => 0.5 * self.constant * mass * softened.ln(),
+            ForceLaw::Newtonian => -self.constant * mass / softened.sqrt(),
+        }
+    }
+
+    /// Speed of a circular orbit at `radius` around a point mass `mass`, from v^2 / r = a.
+    pub fn circular_speed(&self, mass: f32, radius: f32) -> f32 {
+        let acc = self.acceleration(Vec2::new(radius, 0.0), mass).length();
+        (acc * radius).sqrt()
+    }
+
+    /// Whether bodies this far apart, with this combined radius, should attract each other.
+    pub fn applies(&self, distance: f32, contact_distance: f32) -> bool {
+        self.softening > 0.0 || distance >= contact_distance
+    }
+}
+
+/// How asteroid-asteroid gravity is evaluated each tick.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum GravitySolver {
+    /// Exact O(n²) pairwise sum, kept as the reference implementation.
+    #[default]
+    BruteForce,
+    /// O(n log n) quadtree approximation. A cell is treated as a single point mass when
+    /// `cell_size / distance < theta`; `theta = 0` degenerates to the exact sum.
+    BarnesHut { theta: f32 },
+}
+
+impl GravitySolver {
+    pub fn barnes_hut() -> Self {
+        GravitySolver::BarnesHut {
+            theta: DEFAULT_OPENING_ANGLE,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            GravitySolver::BruteForce => "Brute force",
+            GravitySolver::BarnesHut { .. } => "Barnes-Hut",
+        }
+    }
+
+    /// Acceleration of every asteroid caused by all the others, in the same order as `asteroids`.
+    /// Each body's sum is always taken in the same order, so `parallel` doesn't change the result.
+    pub fn accelerations(
+        &self,
+        asteroids: &[Asteroid],
+        gravity: &Gravity,
+        parallel: bool,
+    ) -> Vec<Vec2> {
+        self.accelerations_periodic(asteroids, gravity, None, parallel)
+    }
+
+    /// Like `accelerations`, with every pair separated by the nearest periodic image
+    /// when `period` is set.
+    pub fn accelerations_periodic(
+        &self,
+        asteroids: &[Asteroid],
+        gravity: &Gravity,
+        period: Option<Vec2>,
+        parallel: bool,
+    ) -> Vec<Vec2> {
+        match *self {
+            GravitySolver::BruteForce => {
+                let body =
+                    |asteroid: &Asteroid| asteroid.acceleration_from(asteroids, gravity, period);
+                if parallel {
+                    asteroids.par_iter().map(body).collect()
+                } else {
+                    asteroids.iter().map(body).collect()
+                }
+            }
+            GravitySolver::BarnesHut { theta } => {
+                let tree = QuadTree::new(asteroids);
+                let body = |i| tree.acceleration(asteroids, i, theta, gravity, period);
+                if parallel {
+                    (0..asteroids.len()).into_par_iter().map(body).collect()
+                } else {
+                    (0..asteroids.len()).map(body).collect()
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// How long each simulation step is.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TimeStepping {
+    /// Every step lasts `1 / tick_rate`.
+    #[default]
+    Fixed,
+    /// Steps shrink during close encounters and grow when nothing is happening.
+    /// Each body limits the step to `accuracy` times the shorter of the time its acceleration
+    /// takes to move it across its own radius and the time it takes to close the gap to its
+    /// nearest neighbour at their relative speed.
+    Adaptive {
+        accuracy: f32,
+        min_step: f32,
+        max_step: f32,
+    },
+}
+
+impl TimeStepping {
+    pub fn adaptive() -> Self {
+        TimeStepping::Adaptive {
+            accuracy: 0.2,
+            min_step: 1e-4,
+            max_step: 0.05,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            TimeStepping::Fixed => "Fixed",
+            TimeStepping::Adaptive { .. } => "Adaptive",
+        }
+    }
+}
+
+/// Time integration scheme used to advance positions and velocities each tick.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Integrator {
+    /// First order, one force evaluation per step.
+    #[default]
+    SemiImplicitEuler,
+    /// Drift-kick-drift leapfrog, second order and symplectic.
+    /// Forces are only evaluated at the middle of each step.
+    Leapfrog,
+    /// Kick-drift-kick velocity Verlet, second order and symplectic.
+    /// The forces at the end of a step are handed on to start the next one.
+    VelocityVerlet,
+    /// Classic fourth order Runge-Kutta. Accurate over short spans but not symplectic,
+    /// so energy slowly drifts over many orbits.
+    Rk4,
+}
+
+impl Integrator {
+    pub fn name(&self) -> &str {
+        match self {
+            Integrator::SemiImplicitEuler => "Euler",
+            Integrator::Leapfrog => "Leapfrog",
+            Integrator::VelocityVerlet => "Verlet",
+            Integrator::Rk4 => "RK4",
+        }
+    }
+
+    /// Whether `step` reads the accelerations at the start of the step.
+    pub fn uses_start_acceleration(&self) -> bool {
+        *self != Integrator::Leapfrog
+    }
+
+    /// Advances `pos` and `vel` by `dt`.
+    /// `acc` holds the accelerations at the current positions when they are already known,
+    /// and `acceleration` maps any set of positions to the acceleration of each body there.
+    /// Returns the accelerations at the new positions when the scheme computed them anyway,
+    /// ready to pass as `acc` to the next step if nothing moves the bodies in between.
+    pub fn step<F>(
+        &self,
+        pos: &mut [Vec2],
+        vel: &mut [Vec2],
+        acc: Option<Vec<Vec2>>,
+        dt: f32,
+        acceleration: F,
+    ) -> Option<Vec<Vec2>>
+    where
+        F: Fn(&[Vec2]) -> Vec<Vec2>,
+    {
+        match self {
+            Integrator::SemiImplicitEuler => {
+                let acc = acc.unwrap_or_else(|| acceleration(pos));
+                for i in 0..pos.len() {
+                    vel[i] += acc[i] * dt;
+                    pos[i] += vel[i] * dt;
+                }
+                None
+            }
+            Integrator::Leapfrog => {
+                for i in 0..pos.len() {
+                    pos[i] += vel[i] * (dt / 2.0);
+                }
+                let acc = acceleration(pos);
+                for i in 0..pos.len() {
+                    vel[i] += acc[i] * dt;
+                    pos[i] += vel[i] * (dt / 2.0);
+                }
+                None
+            }
+            Integrator::VelocityVerlet => {
+                let acc = acc.unwrap_or_else(|| acceleration(pos));
+                for i in 0..pos.len() {
+                    vel[i] += acc[i] * (dt / 2.0);
+                    pos[i] += vel[i] * dt;
+                }
+                let new_acc = acceleration(pos);
+                for i in 0..pos.len() {
+                    vel[i] += new_acc[i] * (dt / 2.0);
+                }
+                Some(new_acc)
+            }
+            Integrator::Rk4 => {
+                let offset = |base: &[Vec2], delta: &[Vec2], scale: f32| -> Vec<Vec2> {
+                    base.iter()
+                        .zip(delta)
+                        .map(|(b, d)| *b + *d * scale)
+                        .collect()
+                };
+
+                let k1_vel = vel.to_vec();
+                let k1_acc = acc.unwrap_or_else(|| acceleration(pos));
+
+                let k2_vel = offset(vel, &k1_acc, dt / 2.0);
+                let k2_acc = acceleration(&offset(pos, &k1_vel, dt / 2.0));
+
+                let k3_vel = offset(vel, &k2_acc, dt / 2.0);
+                let k3_acc = acceleration(&offset(pos, &k2_vel, dt / 2.0));
+
+                let k4_vel = offset(vel, &k3_acc, dt);
+                let k4_acc = acceleration(&offset(pos, &k3_vel, dt));
+
+                for i in 0..pos.len() {
+                    pos[i] +=
+                        (k1_vel[i] + 2.0 * k2_vel[i] + 2.0 * k3_vel[i] + k4_vel[i]) * (dt / 6.0);
+                    vel[i] +=
+                        (k1_acc[i] + 2.0 * k2_acc[i] + 2.0 * k3_acc[i] + k4_acc[i]) * (dt / 6.0);
+                }
+                None
+            }
+        }
+    }
+}
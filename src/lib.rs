@@ -1,6 +1,23 @@
+pub mod boundary;
+pub mod broad_phase;
+pub mod clusters;
+pub mod collision;
 pub mod color;
+pub mod diagnostics;
+pub mod events;
+pub mod fragmentation;
 pub mod framebuffer;
+pub mod gravity;
+pub mod integrator;
+pub mod lineage;
+pub mod material;
+pub mod motion;
 pub mod objects;
+pub mod orbit;
+pub mod prediction;
+pub mod quadtree;
+pub mod replay;
 pub mod ship;
+pub mod snapshot;
 pub mod spawn_strategy;
 pub mod world;
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How a body formed out of its parents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Origin {
+    Merge,
+    Fragment,
+}
+
+/// Formation of one body.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineageEntry {
+    pub parents: Vec<u64>,
+    pub world_time: f32,
+    pub origin: Origin,
+}
+
+/// History of every merge and fragmentation, keyed by asteroid ID.
+/// Bodies without an entry were spawned directly, these are the seed bodies.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lineage {
+    entries: BTreeMap<u64, LineageEntry>,
+    children: BTreeMap<u64, Vec<u64>>,
+}
+
+impl Lineage {
+    pub fn record(&mut self, id: u64, parents: &[u64], world_time: f32, origin: Origin) {
+        for &parent in parents {
+            self.children.entry(parent).or_default().push(id);
+        }
+        self.entries.insert(
+            id,
+            LineageEntry {
+                parents: parents.to_vec(),
+                world_time,
+                origin,
+            },
+        );
+    }
+
+    /// How `id` formed, `None` for seed bodies and unknown IDs.
+    pub fn entry(&self, id: u64) -> Option<&LineageEntry> {
+        self.entries.get(&id)
+    }
+
+    pub fn parents(&self, id: u64) -> &[u64] {
+        self.entries
+            .get(&id)
+            .map_or(&[], |entry| entry.parents.as_slice())
+    }
+
+    /// Bodies that `id` merged or fragmented into.
+    pub fn children(&self, id: u64) -> &[u64] {
+        self.children.get(&id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every body that went into `id`, in ascending order.
+    pub fn ancestors(&self, id: u64) -> Vec<u64> {
+        self.collect(id, |id| self.parents(id))
+    }
+
+    /// Every body that `id` went into, in ascending order.
+    pub fn descendants(&self, id: u64) -> Vec<u64> {
+        self.collect(id, |id| self.children(id))
+    }
+
+    /// Seed bodies that ended up in `id`, in ascending order. A seed body is its own only seed.
+    pub fn seed_bodies(&self, id: u64) -> Vec<u64> {
+        if !self.entries.contains_key(&id) {
+            return vec![id];
+        }
+        self.ancestors(id)
+            .into_iter()
+            .filter(|ancestor| !self.entries.contains_key(ancestor))
+            .collect()
+    }
+
+    /// Number of recorded merges and fragments.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Walks the graph from `id` without revisiting bodies reachable along several paths
+    fn collect<'a>(&'a self, id: u64, next: impl Fn(u64) -> &'a [u64]) -> Vec<u64> {
+        let mut found = std::collections::BTreeSet::new();
+        let mut stack = next(id).to_vec();
+        while let Some(current) = stack.pop() {
+            if found.insert(current) {
+                stack.extend_from_slice(next(current));
+            }
+        }
+        found.into_iter().collect()
+    }
+}
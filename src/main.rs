@@ -1,18 +1,25 @@
-mod color;
-mod framebuffer;
-mod objects;
-mod ship;
-mod spawn_strategy;
-mod world;
-
-use color::Color;
-use framebuffer::FrameBuffer;
-use glam::vec2;
-use objects::Asteroid;
-use pixels::{Pixels, SurfaceTexture};
-use spawn_strategy::{
+use asteroids::boundary::Boundary;
+use asteroids::clusters::{Clustering, Group};
+use asteroids::collision::{CollisionResponse, Contact};
+use asteroids::color::Color;
+use asteroids::events::EventKind;
+use asteroids::fragmentation::Fragmentation;
+use asteroids::framebuffer::{self, FrameBuffer};
+use asteroids::gravity::{ForceLaw, GravitySolver};
+use asteroids::integrator::{Integrator, TimeStepping};
+use asteroids::material::Material;
+use asteroids::motion::Motion;
+use asteroids::objects::Asteroid;
+use asteroids::orbit;
+use asteroids::prediction::{self, PredictionSettings, ShipPrediction};
+use asteroids::replay::{Input, Recording, Replay};
+use asteroids::snapshot::Snapshot;
+use asteroids::spawn_strategy::{
     OrbitalDiskStrategy, RandomScreenSpaceStrategy, SolarSystemStrategy, SpawnStrategy,
 };
+use asteroids::world::WorldState;
+use glam::vec2;
+use pixels::{Pixels, SurfaceTexture};
 use std::pin::Pin;
 use std::time::Duration;
 use std::time::Instant;
@@ -24,13 +31,32 @@ use winit::{
     keyboard::{KeyCode, PhysicalKey},
     window::{Window, WindowId},
 };
-use world::WorldState;
 const RANDOM_SPAWN_RATE_INITIAL: f32 = 10.0;
 const RANDOM_SPAWN_RATE_INCREASE: f32 = 2.0;
 const STATS_UPDATE_RATE: f32 = 5.0;
 const FPS_TARGET: f32 = 60.0;
 const SPEED_ADJUST_FACTOR: f32 = 1.5;
 const MAX_SPEED_MULTIPLIER_RATIO: f32 = 5.0;
+const OPENING_ANGLE_STEP: f32 = 0.1;
+const NOTICE_DURATION: f32 = 2.0;
+const MERGE_SPEED: f32 = 20.0;
+const MIN_PREDICTION_HORIZON: f32 = 1.0;
+const MAX_PREDICTION_HORIZON: f32 = 600.0;
+const QUICKSAVE_PATH: &str = "quicksave.bin";
+const QUICKSAVE_JSON_PATH: &str = "quicksave.json";
+const RECORDING_PATH: &str = "recording.bin";
+const BOUNDARY_COLOR: Color = Color {
+    r: 90,
+    g: 90,
+    b: 140,
+    a: 255,
+};
+const CONSTRAINED_COLOR: Color = Color {
+    r: 240,
+    g: 170,
+    b: 90,
+    a: 255,
+};
 
 fn format_time(seconds: f32) -> String {
     let total_seconds = seconds as i64;
@@ -54,17 +80,21 @@ fn format_time(seconds: f32) -> String {
 
 enum AppState {
     Starting,
-    Running(RunningState),
+    Running(Box<RunningState>),
 }
 
 struct App {
     state: AppState,
+    seed: Option<u64>,
+    replay: Option<Recording>,
 }
 
 impl Default for App {
     fn default() -> Self {
         Self {
             state: AppState::Starting,
+            seed: None,
+            replay: None,
         }
     }
 }
@@ -79,15 +109,43 @@ struct RunningState {
     last_fps_time: Instant,
     frames_per_second: f32,
     stats_changed: bool,
+    // Conservation HUD line, `None` while hidden. Refreshed with the other stats since it's O(n²)
+    conservation_text: Option<String>,
+    // Latest ship event for the HUD, hidden after NOTICE_DURATION
+    notice: Option<(String, Instant)>,
+    // Ship trajectory overlay in ShipControl mode, `None` while hidden
+    prediction: Option<PredictionSettings>,
+    // Groups behind the group camera, refreshed with the other stats since finding them is
+    // O(n²). `None` until needed
+    groups: Option<Vec<Group>>,
+    // Orbital elements HUD lines, refreshed with the other stats since the Hill hierarchy
+    // behind them is O(n²). `None` until first shown
+    orbit_text: Option<Vec<String>>,
+    // Latest prediction, refreshed with the other stats and dropped when it goes out of date
+    ship_prediction: Option<ShipPrediction>,
+    // What hand-placed asteroids are made of
+    placing_material: Material,
     last_update_time: Instant,
     random_spawn_timer: f32,
     random_spawn_hold_time: f32,
     window_visible: bool,
     spawn_strategy: Box<dyn SpawnStrategy>,
+    recording: Option<Recording>,
+    // While set, the world only follows the recording and live inputs are ignored
+    replay: Option<Replay>,
+}
+
+// Inputs that only change how fast the app advances the world
+fn apply_speed_input(framebuffer: &mut FrameBuffer, input: &Input) {
+    match input {
+        Input::SetSpeed(speed) => framebuffer.speed_multiplier = *speed,
+        Input::TogglePause => framebuffer.toggle_pause(),
+        _ => {}
+    }
 }
 
 impl RunningState {
-    fn new(window: Window) -> Self {
+    fn new(window: Window, seed: Option<u64>, recording: Option<Recording>) -> Self {
         let window = Box::pin(window);
         let w_ref: &Window = window.as_ref().get_ref();
 
@@ -109,11 +167,31 @@ impl RunningState {
             .expect("Failed to load ship sprite")
             .to_rgba8();
 
+        let (mut world, spawn_strategy, replay) = match recording {
+            Some(recording) => {
+                println!("Replaying {} recorded inputs", recording.inputs.len());
+                let (world, strategy) = recording.start_state();
+                (world, strategy, Some(Replay::new(recording)))
+            }
+            None => {
+                let world = match seed {
+                    Some(seed) => WorldState::with_seed(seed),
+                    None => WorldState::new(),
+                };
+                // Printed so a session can be reproduced with --seed
+                println!("World seed: {}", world.seed());
+                let strategy: Box<dyn SpawnStrategy> = Box::new(OrbitalDiskStrategy::new());
+                (world, strategy, None)
+            }
+        };
+
+        world.events().set_queued(true);
+
         let now = Instant::now();
         Self {
             framebuffer,
             window,
-            world: WorldState::new(),
+            world,
             ship_sprite,
             last_frame_time: now,
             frame_count: 0,
@@ -124,7 +202,16 @@ impl RunningState {
             random_spawn_hold_time: 0.0,
             window_visible: true,
             stats_changed: false,
-            spawn_strategy: Box::new(OrbitalDiskStrategy::new()),
+            conservation_text: None,
+            notice: None,
+            prediction: Some(PredictionSettings::default()),
+            groups: None,
+            orbit_text: None,
+            ship_prediction: None,
+            placing_material: Material::default(),
+            spawn_strategy,
+            recording: None,
+            replay,
         }
     }
 
@@ -147,31 +234,38 @@ impl RunningState {
         clear_color.a = 0;
         self.framebuffer.clear(clear_color);
 
-        for asteroid in &self.world.asteroids {
-            asteroid.draw(&mut self.framebuffer, Color::WHITE);
+        // In a toroidal world every body is drawn once per visible copy of the box,
+        // which is the same as drawing the box once per shifted camera
+        let boundary = self.world.boundary();
+        let camera_pos = self.framebuffer.camera_pos;
+        let view_min = self.framebuffer.screen_to_world(vec2(0.0, 0.0));
+        let view_max = self.framebuffer.screen_to_world(vec2(
+            self.framebuffer.width() as f32,
+            self.framebuffer.height() as f32,
+        ));
+        for offset in boundary.image_offsets(view_min, view_max) {
+            self.framebuffer.camera_pos = camera_pos - offset;
+            self.draw_bodies();
         }
-
-        // Draw ship engine flame if firing (draw before ship so it appears behind)
-        if self.world.ship.engine_power > 0.0 {
-            let flame_length = 15.0 * self.world.ship.engine_power;
-            let cos_angle = self.world.ship.orientation.cos();
-            let sin_angle = self.world.ship.orientation.sin();
-            let flame_offset = vec2(sin_angle, -cos_angle) * (-10.0 - flame_length / 2.0);
-            let flame_pos = self.world.ship.pos + flame_offset;
-            let flame_color = Color {
-                r: 100,
-                g: 150,
-                b: 255,
-                a: (200.0 * self.world.ship.engine_power) as u8,
-            };
-            self.framebuffer
-                .draw_circle(flame_pos, flame_length / 2.0, flame_color);
+        self.framebuffer.camera_pos = camera_pos;
+
+        if let Some((min, max)) = boundary.bounds() {
+            let corners = [min, vec2(max.x, min.y), max, vec2(min.x, max.y)];
+            for k in 0..corners.len() {
+                let next = corners[(k + 1) % corners.len()];
+                self.framebuffer
+                    .draw_world_line(corners[k], next, BOUNDARY_COLOR);
+            }
         }
 
-        // Draw ship (only once)
-        self.world
-            .ship
-            .draw(&mut self.framebuffer, &self.ship_sprite);
+        if self.framebuffer.camera_mode == framebuffer::CameraMode::ShipControl {
+            if self.ship_prediction.is_none() {
+                self.refresh_prediction();
+            }
+            if let Some(ship_prediction) = &self.ship_prediction {
+                ship_prediction.draw(&mut self.framebuffer, self.world.ship.radius());
+            }
+        }
 
         if self.framebuffer.creating_asteroid {
             let screen_center = vec2(
@@ -194,40 +288,99 @@ impl RunningState {
         self.framebuffer
             .draw_text(&stats_text, text_pos, 16.0, Color::WHITE);
 
-        let time_text = format!(
+        let mut time_text = format!(
             "Time: {} | Asteroids: {}",
             format_time(self.world.world_time),
             self.world.asteroids.len()
         );
+        if self.replay.is_some() {
+            time_text.push_str(" | Replay");
+        } else if self.recording.is_some() {
+            time_text.push_str(" | REC");
+        }
         let time_pos = vec2(10.0, 30.0);
         self.framebuffer
             .draw_text(&time_text, time_pos, 16.0, Color::WHITE);
 
+        if let Some(conservation_text) = &self.conservation_text {
+            let conservation_pos = vec2(10.0, 50.0);
+            self.framebuffer
+                .draw_text(conservation_text, conservation_pos, 16.0, Color::WHITE);
+        }
+
+        if let Some((notice, shown)) = &self.notice
+            && shown.elapsed().as_secs_f32() < NOTICE_DURATION
+        {
+            let notice_pos = vec2(10.0, 70.0);
+            self.framebuffer
+                .draw_text(notice, notice_pos, 16.0, Color::WHITE);
+        }
+
         let window_size = self.window().inner_size();
 
         let speed_text = format!(
-            "Speed: {:.1}x | Zoom: {:.2}x",
-            self.framebuffer.speed_multiplier, self.framebuffer.zoom
+            "Speed: {:.1}x (sim {:.1}x) | Zoom: {:.2}x",
+            self.framebuffer.speed_multiplier,
+            self.world.simulated_time_ratio(),
+            self.framebuffer.zoom
         );
         let text_width = speed_text.len() as f32 * 10.0;
         let speed_pos = vec2(window_size.width as f32 - text_width - 10.0, 10.0);
         self.framebuffer
             .draw_text(&speed_text, speed_pos, 16.0, Color::WHITE);
 
-        let mode_text = format!(
+        let mut mode_text = format!(
             "Spawn: {} | Camera: {}",
             self.spawn_strategy.name(),
             self.framebuffer.camera_mode.name()
         );
+        if self.framebuffer.camera_mode == framebuffer::CameraMode::ShipControl
+            && let Some(settings) = &self.prediction
+        {
+            mode_text += &format!(" | Predict: {:.0}s", settings.horizon);
+        }
         let mode_text_width = mode_text.len() as f32 * 10.0;
         let mode_pos = vec2(window_size.width as f32 - mode_text_width - 10.0, 30.0);
         self.framebuffer
             .draw_text(&mode_text, mode_pos, 16.0, Color::WHITE);
 
+        let gravity_text = match self.world.gravity_solver() {
+            GravitySolver::BarnesHut { theta } => format!("Gravity: Barnes-Hut θ={:.1}", theta),
+            solver => format!("Gravity: {}", solver.name()),
+        };
+        let gravity_text = format!(
+            "{} {} | Integrator: {}",
+            gravity_text,
+            self.world.gravity().law.name(),
+            self.world.integrator().name()
+        );
+        let gravity_text_width = gravity_text.chars().count() as f32 * 10.0;
+        let gravity_pos = vec2(window_size.width as f32 - gravity_text_width - 10.0, 50.0);
+        self.framebuffer
+            .draw_text(&gravity_text, gravity_pos, 16.0, Color::WHITE);
+
+        let collision_text = format!(
+            "Step: {} {:.4}s | Impacts: {}{} | Bounds: {}",
+            self.world.time_stepping().name(),
+            self.world.last_step(),
+            self.world.collision_response().name(),
+            if self.world.fragmentation().is_some() {
+                " + Fragment"
+            } else {
+                ""
+            },
+            self.world.boundary().name()
+        );
+        let collision_text_width = collision_text.len() as f32 * 10.0;
+        let collision_pos = vec2(window_size.width as f32 - collision_text_width - 10.0, 70.0);
+        self.framebuffer
+            .draw_text(&collision_text, collision_pos, 16.0, Color::WHITE);
+
         // Draw engine power indicator (bottom left)
         if self.framebuffer.camera_mode == framebuffer::CameraMode::ShipControl {
             self.world.ship.draw_engine_indicator(&mut self.framebuffer);
             self.world.ship.draw_health_bar(&mut self.framebuffer);
+            self.draw_orbit_info();
         }
 
         self.framebuffer.render().unwrap();
@@ -240,9 +393,261 @@ impl RunningState {
             self.frame_count = 0;
             self.last_fps_time = Instant::now();
             self.stats_changed = true;
+
+            if self.conservation_text.is_some() {
+                self.conservation_text = Some(self.format_conservation());
+            }
+            self.refresh_prediction();
+            self.orbit_text = (self.framebuffer.camera_mode
+                == framebuffer::CameraMode::ShipControl)
+                .then(|| self.format_orbit());
+            self.groups = None;
         }
     }
 
+    // Looking far ahead takes thousands of steps, too many to repeat every frame
+    fn refresh_prediction(&mut self) {
+        self.ship_prediction = match &self.prediction {
+            Some(settings)
+                if self.framebuffer.camera_mode == framebuffer::CameraMode::ShipControl =>
+            {
+                Some(prediction::predict_ship_path(&self.world, settings))
+            }
+            _ => None,
+        };
+    }
+
+    fn draw_bodies(&mut self) {
+        for asteroid in &self.world.asteroids {
+            let color = if self.world.motion(asteroid.id()).is_some() {
+                CONSTRAINED_COLOR
+            } else {
+                asteroid.composition().color()
+            };
+            asteroid.draw(&mut self.framebuffer, color);
+        }
+
+        // Draw ship engine flame if firing (draw before ship so it appears behind)
+        if self.world.ship.engine_power > 0.0 {
+            let flame_length = 15.0 * self.world.ship.engine_power;
+            let cos_angle = self.world.ship.orientation.cos();
+            let sin_angle = self.world.ship.orientation.sin();
+            let flame_offset = vec2(sin_angle, -cos_angle) * (-10.0 - flame_length / 2.0);
+            let flame_pos = self.world.ship.pos + flame_offset;
+            let flame_color = Color {
+                r: 100,
+                g: 150,
+                b: 255,
+                a: (200.0 * self.world.ship.engine_power) as u8,
+            };
+            self.framebuffer
+                .draw_circle(flame_pos, flame_length / 2.0, flame_color);
+        }
+
+        // Draw ship, once per visible copy of the box like everything else
+        self.world
+            .ship
+            .draw(&mut self.framebuffer, &self.ship_sprite);
+    }
+
+    // Right-aligned next to the health bar, which takes the bottom right 30 pixels
+    fn draw_orbit_info(&mut self) {
+        if self.orbit_text.is_none() {
+            self.orbit_text = Some(self.format_orbit());
+        }
+        let lines = self.orbit_text.as_deref().unwrap_or_default();
+        let right = self.framebuffer.width() as f32 - 40.0;
+        let bottom = self.framebuffer.height() as f32 - 30.0;
+        for (k, line) in lines.iter().rev().enumerate() {
+            let width = line.chars().count() as f32 * 10.0;
+            let pos = vec2(right - width, bottom - k as f32 * 20.0);
+            self.framebuffer.draw_text(line, pos, 16.0, Color::WHITE);
+        }
+    }
+
+    fn format_orbit(&self) -> Vec<String> {
+        match orbit::ship_orbit(&self.world) {
+            Some(elements) => {
+                let apoapsis = elements
+                    .apoapsis
+                    .map_or("-".to_string(), |apoapsis| format!("{:.0}", apoapsis));
+                let period = elements.period.map_or("-".to_string(), format_time);
+                vec![
+                    format!("Orbiting #{}", elements.primary),
+                    format!(
+                        "a: {:.0} e: {:.3}",
+                        elements.semi_major_axis, elements.eccentricity
+                    ),
+                    format!("Pe: {:.0} Ap: {}", elements.periapsis, apoapsis),
+                    format!(
+                        "T: {} ω: {:.0}°",
+                        period,
+                        elements.argument_of_periapsis.to_degrees()
+                    ),
+                ]
+            }
+            None => vec!["No orbit".to_string()],
+        }
+    }
+
+    fn format_conservation(&self) -> String {
+        let conservation = self.world.conservation();
+        let mut text = format!(
+            "E: {:.3e} | P: ({:.1}, {:.1}) | L: {:.3e}",
+            conservation.total_energy(),
+            conservation.momentum.x,
+            conservation.momentum.y,
+            conservation.angular_momentum
+        );
+        if let Some(drift) = self.world.conservation_drift() {
+            text += &format!(
+                " | dE: {:+.2e} dP: {:.1} dL: {:+.2e}",
+                drift.energy,
+                drift.momentum.length(),
+                drift.angular_momentum
+            );
+            if drift.ejected_mass > 0.0 {
+                text += &format!(
+                    " | Ejected: m={:.1} P={:.1} (dP unexplained: {:.1})",
+                    drift.ejected_mass,
+                    drift.ejected_momentum.length(),
+                    drift.unexplained_momentum().length()
+                );
+            }
+        }
+        text
+    }
+
+    fn toggle_conservation_hud(&mut self) {
+        self.conservation_text = match self.conservation_text {
+            Some(_) => None,
+            None => Some(self.format_conservation()),
+        };
+    }
+
+    fn quick_save(&self, path: &str) {
+        let snapshot = Snapshot::new(self.world.snapshot(), Some(self.spawn_strategy.snapshot()));
+        match snapshot.save(path) {
+            Ok(()) => println!("Saved world to {}", path),
+            Err(error) => eprintln!("Failed to save world to {}: {}", path, error),
+        }
+    }
+
+    fn quick_load(&mut self, path: &str) {
+        match Snapshot::load(path) {
+            Ok(snapshot) => {
+                // The recording can't describe the jump, so it ends here
+                if self.recording.is_some() {
+                    self.toggle_recording();
+                }
+                self.replay = None;
+
+                self.world = WorldState::from_snapshot(snapshot.world);
+                self.world.events().set_queued(true);
+                self.ship_prediction = None;
+                self.orbit_text = None;
+                self.groups = None;
+                if let Some(strategy) = snapshot.strategy {
+                    self.spawn_strategy = strategy.into_strategy();
+                }
+                if self.conservation_text.is_some() {
+                    self.conservation_text = Some(self.format_conservation());
+                }
+                self.stats_changed = true;
+                println!("Loaded world from {}", path);
+            }
+            Err(error) => eprintln!("Failed to load world from {}: {}", path, error),
+        }
+    }
+
+    /// Applies an input to the world, recording it first if a recording is running.
+    fn apply_input(&mut self, input: Input) {
+        if self.replay.is_some() {
+            return;
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.record(&self.world, &input);
+        }
+        apply_speed_input(&mut self.framebuffer, &input);
+        input.apply(&mut self.world, &mut self.spawn_strategy);
+    }
+
+    fn advance(&mut self, duration: f32) -> f32 {
+        if let Some(recording) = &mut self.recording {
+            recording.record(&self.world, &Input::Advance(duration));
+        }
+        self.world.update(duration)
+    }
+
+    fn process_events(&mut self) {
+        for event in self.world.events().drain() {
+            let notice = match event.kind {
+                EventKind::ShipCollided { damage, .. } if damage > 0.0 => {
+                    format!("Hull hit: -{:.0}", damage)
+                }
+                EventKind::ShipDied { .. } => "Ship destroyed".to_string(),
+                EventKind::ShipRespawned { .. } => "Ship destroyed, respawned".to_string(),
+                _ => continue,
+            };
+            self.notice = Some((notice, Instant::now()));
+        }
+    }
+
+    fn change_speed(&mut self, change: fn(&mut FrameBuffer)) {
+        if self.replay.is_some() {
+            return;
+        }
+        change(&mut self.framebuffer);
+        self.apply_input(Input::SetSpeed(self.framebuffer.speed_multiplier));
+        self.stats_changed = true;
+    }
+
+    fn toggle_recording(&mut self) {
+        match self.recording.take() {
+            Some(recording) => match recording.save(RECORDING_PATH) {
+                Ok(()) => println!(
+                    "Saved {} recorded inputs to {}",
+                    recording.inputs.len(),
+                    RECORDING_PATH
+                ),
+                Err(error) => {
+                    eprintln!("Failed to save recording to {}: {}", RECORDING_PATH, error)
+                }
+            },
+            None if self.replay.is_none() => {
+                self.recording = Some(Recording::new(&self.world, self.spawn_strategy.as_ref()));
+                println!("Recording inputs");
+            }
+            None => {}
+        }
+        self.stats_changed = true;
+    }
+
+    fn play_replay_frame(&mut self) {
+        let Some(replay) = &mut self.replay else {
+            return;
+        };
+
+        let desync = match replay.play_frame(&mut self.world, &mut self.spawn_strategy) {
+            Ok(inputs) => {
+                for recorded in inputs {
+                    apply_speed_input(&mut self.framebuffer, &recorded.input);
+                }
+                None
+            }
+            Err(desync) => Some(desync),
+        };
+
+        if let Some(desync) = desync {
+            eprintln!("Stopping replay, {}", desync);
+            self.replay = None;
+        } else if replay.is_finished() {
+            println!("Replay finished at tick {}", self.world.tick());
+            self.replay = None;
+        }
+        self.stats_changed = true;
+    }
+
     fn on_press(&mut self) {
         if !self.framebuffer.creating_asteroid {
             let screen_pos = self.framebuffer.cursor_pos;
@@ -255,27 +660,245 @@ impl RunningState {
             let screen_pos = self.framebuffer.cursor_pos;
             let (pos, vel, size) = self
                 .framebuffer
-                .finish_creating_asteroid(screen_pos, self.world.actual_speed());
-            self.world.spawn_asteroid(pos, vel, size);
+                .finish_creating_asteroid(screen_pos, self.world.simulated_time_ratio());
+            self.apply_input(Input::PlaceAsteroid {
+                pos,
+                vel,
+                size,
+                material: self.placing_material,
+            });
         }
     }
 
+    fn cycle_placing_material(&mut self) {
+        let materials = Material::ALL;
+        let current = materials
+            .iter()
+            .position(|&material| material == self.placing_material)
+            .unwrap_or(0);
+        self.placing_material = materials[(current + 1) % materials.len()];
+        self.notice = Some((
+            format!("Placing: {}", self.placing_material.name()),
+            Instant::now(),
+        ));
+    }
+
     fn spawn_asteroids(&mut self) {
-        self.spawn_strategy
-            .spawn(&mut self.world, &self.framebuffer);
+        let view = self
+            .framebuffer
+            .spawn_view(self.world.simulated_time_ratio());
+        self.apply_input(Input::Spawn(view));
     }
 
     fn toggle_spawn_strategy(&mut self) {
         let current_name = self.spawn_strategy.name();
-        self.spawn_strategy = match current_name {
+        let strategy: Box<dyn SpawnStrategy> = match current_name {
             "Random" => Box::new(OrbitalDiskStrategy::new()),
             "Orbital" => Box::new(SolarSystemStrategy::new()),
             "Solar System" => Box::new(RandomScreenSpaceStrategy::new()),
             _ => Box::new(RandomScreenSpaceStrategy::new()),
         };
+        self.apply_input(Input::SetStrategy(strategy.snapshot()));
+        self.stats_changed = true;
+    }
+
+    fn toggle_gravity_solver(&mut self) {
+        let solver = match self.world.gravity_solver() {
+            GravitySolver::BruteForce => GravitySolver::barnes_hut(),
+            GravitySolver::BarnesHut { .. } => GravitySolver::BruteForce,
+        };
+        self.apply_input(Input::SetGravitySolver(solver));
+        self.stats_changed = true;
+    }
+
+    fn cycle_integrator(&mut self) {
+        let integrator = match self.world.integrator() {
+            Integrator::SemiImplicitEuler => Integrator::Leapfrog,
+            Integrator::Leapfrog => Integrator::VelocityVerlet,
+            Integrator::VelocityVerlet => Integrator::Rk4,
+            Integrator::Rk4 => Integrator::SemiImplicitEuler,
+        };
+        self.apply_input(Input::SetIntegrator(integrator));
+        self.stats_changed = true;
+    }
+
+    fn toggle_force_law(&mut self) {
+        let mut gravity = self.world.gravity();
+        gravity.law = match gravity.law {
+            ForceLaw::Logarithmic => ForceLaw::Newtonian,
+            ForceLaw::Newtonian => ForceLaw::Logarithmic,
+        };
+        self.apply_input(Input::SetGravity(gravity));
+        self.stats_changed = true;
+    }
+
+    fn toggle_time_stepping(&mut self) {
+        let time_stepping = match self.world.time_stepping() {
+            TimeStepping::Fixed => TimeStepping::adaptive(),
+            TimeStepping::Adaptive { .. } => TimeStepping::Fixed,
+        };
+        self.apply_input(Input::SetTimeStepping(time_stepping));
+        self.stats_changed = true;
+    }
+
+    fn toggle_fragmentation(&mut self) {
+        let fragmentation = match self.world.fragmentation() {
+            Some(_) => None,
+            None => Some(Fragmentation::default()),
+        };
+        self.apply_input(Input::SetFragmentation(fragmentation));
+        self.stats_changed = true;
+    }
+
+    fn cycle_collision_response(&mut self) {
+        let contact = Contact::default();
+        let response = match self.world.collision_response() {
+            CollisionResponse::Merge => CollisionResponse::Bounce(contact),
+            CollisionResponse::Bounce(_) => CollisionResponse::MergeBelow {
+                speed: MERGE_SPEED,
+                contact,
+            },
+            CollisionResponse::MergeBelow { .. } => CollisionResponse::Merge,
+        };
+        self.apply_input(Input::SetCollisionResponse(response));
+        self.stats_changed = true;
+    }
+
+    // Boxes are fitted to the current view when switched on
+    fn cycle_boundary(&mut self) {
+        let center = self.framebuffer.camera_pos;
+        let size = vec2(
+            self.framebuffer.width() as f32,
+            self.framebuffer.height() as f32,
+        ) / self.framebuffer.zoom;
+        let boundary = match self.world.boundary() {
+            Boundary::Cleanup { .. } => Boundary::Open,
+            Boundary::Open => Boundary::Toroidal { center, size },
+            Boundary::Toroidal { .. } => Boundary::Reflective { center, size },
+            Boundary::Reflective { .. } => Boundary::default(),
+        };
+        self.apply_input(Input::SetBoundary(boundary));
+        self.stats_changed = true;
+    }
+
+    // Free -> pinned -> circling its dominant body at its current angular velocity -> free
+    fn cycle_motion_under_cursor(&mut self) {
+        let cursor = self
+            .framebuffer
+            .screen_to_world(self.framebuffer.cursor_pos);
+        let boundary = self.world.boundary();
+        let Some(asteroid) = self.world.asteroids.iter().copied().min_by(|a, b| {
+            let distance = |body: &Asteroid| boundary.separation(cursor, body.pos()).length();
+            distance(a).total_cmp(&distance(b))
+        }) else {
+            return;
+        };
+
+        let motion = match self.world.motion(asteroid.id()) {
+            None => Some(Motion::Pinned {
+                pos: asteroid.pos(),
+            }),
+            Some(Motion::Pinned { .. }) => orbit::dominant_body(
+                &self.world,
+                asteroid.pos(),
+                asteroid.size(),
+                Some(asteroid.id()),
+            )
+            .and_then(|primary| {
+                let offset = boundary.separation(primary.pos(), asteroid.pos());
+                let vel = asteroid.vel() - primary.vel();
+                let angular_velocity = offset.perp_dot(vel) / offset.length_squared();
+                angular_velocity.is_finite().then(|| {
+                    Motion::orbit_through(
+                        primary.pos(),
+                        primary.pos() + offset,
+                        angular_velocity,
+                        self.world.world_time,
+                    )
+                })
+            }),
+            Some(_) => None,
+        };
+        let notice = format!(
+            "Asteroid #{}: {}",
+            asteroid.id(),
+            motion.as_ref().map_or("Free", Motion::name)
+        );
+        self.apply_input(Input::SetMotion {
+            id: asteroid.id(),
+            motion,
+        });
+        self.notice = Some((notice, Instant::now()));
+        self.stats_changed = true;
+    }
+
+    /// The group containing `id`, or the body it has since merged into, as it is now.
+    fn tracked_group(&mut self, id: u64) -> Option<Group> {
+        let alive = if self.world.asteroid(id).is_some() {
+            Some(id)
+        } else {
+            let descendants = self.world.lineage().descendants(id);
+            descendants
+                .into_iter()
+                .rev()
+                .find(|&descendant| self.world.asteroid(descendant).is_some())
+        }?;
+        // Membership comes from the cache, bodies that merged since need a fresh search
+        let groups = self
+            .groups
+            .get_or_insert_with(|| self.world.groups(Clustering::default()));
+        if !groups.iter().any(|group| group.contains(alive)) {
+            *groups = self.world.groups(Clustering::default());
+        }
+        groups
+            .iter()
+            .find(|group| group.contains(alive))?
+            .refreshed(&self.world)
+    }
+
+    // Steps through the groups from the heaviest down
+    fn cycle_tracked_group(&mut self) {
+        let groups = self.groups.insert(self.world.groups(Clustering::default()));
+        if groups.is_empty() {
+            return;
+        }
+        let next = match self.framebuffer.camera_mode {
+            framebuffer::CameraMode::TrackingGroup(id) => groups
+                .iter()
+                .position(|group| group.contains(id))
+                .map_or(0, |current| (current + 1) % groups.len()),
+            _ => 0,
+        };
+        self.framebuffer.camera_mode = framebuffer::CameraMode::TrackingGroup(groups[next].primary);
+        self.stats_changed = true;
+    }
+
+    fn toggle_prediction(&mut self) {
+        self.prediction = match self.prediction {
+            Some(_) => None,
+            None => Some(PredictionSettings::default()),
+        };
+        self.ship_prediction = None;
         self.stats_changed = true;
     }
 
+    fn scale_prediction_horizon(&mut self, factor: f32) {
+        if let Some(settings) = &mut self.prediction {
+            settings.horizon =
+                (settings.horizon * factor).clamp(MIN_PREDICTION_HORIZON, MAX_PREDICTION_HORIZON);
+            self.ship_prediction = None;
+            self.stats_changed = true;
+        }
+    }
+
+    fn adjust_opening_angle(&mut self, delta: f32) {
+        if let GravitySolver::BarnesHut { theta } = self.world.gravity_solver() {
+            let theta = (theta + delta).clamp(0.0, 2.0);
+            self.apply_input(Input::SetGravitySolver(GravitySolver::BarnesHut { theta }));
+            self.stats_changed = true;
+        }
+    }
+
     fn update(&mut self) {
         let mut dt = Instant::now()
             .duration_since(self.last_frame_time)
@@ -298,11 +921,21 @@ impl RunningState {
         match self.framebuffer.camera_mode {
             framebuffer::CameraMode::Manual => {
                 self.framebuffer.update_camera(dt);
+                self.framebuffer.camera_pos =
+                    self.world.boundary().wrap(self.framebuffer.camera_pos);
             }
             framebuffer::CameraMode::TrackingCenterOfMass => {
                 let center = self.world.calculate_center_of_mass(true);
                 self.framebuffer.camera_pos = center;
             }
+            framebuffer::CameraMode::TrackingGroup(id) => {
+                if let Some(group) = self.tracked_group(id) {
+                    self.framebuffer.camera_pos = group.barycenter;
+                    // Follow the heaviest member, the others may merge away
+                    self.framebuffer.camera_mode =
+                        framebuffer::CameraMode::TrackingGroup(group.primary);
+                }
+            }
             framebuffer::CameraMode::ShipControl => {
                 // Apply ship controls
                 let mut rcs_forward = 0.0;
@@ -334,39 +967,47 @@ impl RunningState {
                     .keys_pressed
                     .contains(&KeyCode::ControlLeft);
 
-                self.world.ship.apply_control(
+                self.apply_input(Input::Control {
                     rcs_forward,
                     rcs_strafe,
                     rotate,
                     engine_increase,
                     engine_decrease,
                     dt,
-                );
+                });
             }
         }
 
         let mut update_secs = elapsed.as_secs_f32();
 
-        if self.framebuffer.speed_multiplier != 0.0 {
+        if self.replay.is_some() {
+            // Replays advance one recorded frame per app frame
+            self.play_replay_frame();
+        } else if self.framebuffer.speed_multiplier != 0.0 {
             // Clamp speed multiplier to prevent simulation from falling behind and reducing FPS
-            let actual_speed = self.world.actual_speed();
-            let max_allowed_speed = (actual_speed * MAX_SPEED_MULTIPLIER_RATIO).max(0.01);
+            let simulated_ratio = self.world.simulated_time_ratio();
+            let max_allowed_speed = (simulated_ratio * MAX_SPEED_MULTIPLIER_RATIO).max(0.01);
             let effective_speed = self.framebuffer.speed_multiplier.min(max_allowed_speed);
 
             // Calculate how much we should update the simulation by
             let scaled_time = elapsed.as_secs_f32() * effective_speed;
-            let scaled_update_time = self.world.update(scaled_time);
+            let scaled_update_time = self.advance(scaled_time);
             update_secs = scaled_update_time / effective_speed.max(0.01);
         }
         let update_time = Duration::from_secs_f32(update_secs);
+        self.process_events();
 
         // Update camera position after world update (for ship control mode)
         if self.framebuffer.camera_mode == framebuffer::CameraMode::ShipControl {
             self.framebuffer.camera_pos = self.world.ship.pos;
         }
 
-        // Update camera velocity after position update
-        self.framebuffer.camera_vel = (self.framebuffer.camera_pos - old_camera_pos) / dt;
+        // Update camera velocity after position update, ignoring jumps across a periodic edge
+        self.framebuffer.camera_vel = self
+            .world
+            .boundary()
+            .separation(old_camera_pos, self.framebuffer.camera_pos)
+            / dt;
 
         self.framebuffer.update_asteroid_size(dt);
 
@@ -433,10 +1074,10 @@ impl ApplicationHandler for App {
             )
             .unwrap();
 
-        let running = RunningState::new(window);
+        let running = RunningState::new(window, self.seed, self.replay.take());
         running.window().request_redraw();
 
-        self.state = AppState::Running(running);
+        self.state = AppState::Running(Box::new(running));
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
@@ -496,6 +1137,10 @@ impl ApplicationHandler for App {
                                 running.stats_changed = true;
                             }
 
+                            if keycode == KeyCode::KeyK {
+                                running.cycle_tracked_group();
+                            }
+
                             if keycode == KeyCode::KeyC {
                                 running.framebuffer.camera_mode = if running.framebuffer.camera_mode
                                     == framebuffer::CameraMode::ShipControl
@@ -508,11 +1153,11 @@ impl ApplicationHandler for App {
                             }
 
                             if keycode == KeyCode::KeyX {
-                                running.world.ship.engine_power = 0.0;
+                                running.apply_input(Input::CutEngine);
                             }
 
                             if keycode == KeyCode::KeyP {
-                                running.framebuffer.toggle_pause();
+                                running.apply_input(Input::TogglePause);
                                 running.stats_changed = true;
                             }
 
@@ -524,6 +1169,55 @@ impl ApplicationHandler for App {
                                 running.toggle_spawn_strategy();
                             }
 
+                            if keycode == KeyCode::KeyG {
+                                running.toggle_gravity_solver();
+                            }
+                            if keycode == KeyCode::KeyH {
+                                running.toggle_conservation_hud();
+                            }
+                            if keycode == KeyCode::KeyB {
+                                running.apply_input(Input::ResetConservationBaseline);
+                            }
+                            if keycode == KeyCode::KeyL {
+                                running.toggle_force_law();
+                            }
+                            if keycode == KeyCode::KeyJ {
+                                running.toggle_time_stepping();
+                            }
+                            if keycode == KeyCode::KeyF {
+                                running.toggle_fragmentation();
+                            }
+                            if keycode == KeyCode::KeyV {
+                                running.toggle_prediction();
+                            }
+                            if keycode == KeyCode::Comma {
+                                running.scale_prediction_horizon(0.5);
+                            }
+                            if keycode == KeyCode::Period {
+                                running.scale_prediction_horizon(2.0);
+                            }
+                            if keycode == KeyCode::KeyU {
+                                running.cycle_collision_response();
+                            }
+                            if keycode == KeyCode::KeyM {
+                                running.cycle_boundary();
+                            }
+                            if keycode == KeyCode::KeyY {
+                                running.cycle_placing_material();
+                            }
+                            if keycode == KeyCode::KeyN {
+                                running.cycle_motion_under_cursor();
+                            }
+                            if keycode == KeyCode::KeyI {
+                                running.cycle_integrator();
+                            }
+                            if keycode == KeyCode::BracketLeft {
+                                running.adjust_opening_angle(-OPENING_ANGLE_STEP);
+                            }
+                            if keycode == KeyCode::BracketRight {
+                                running.adjust_opening_angle(OPENING_ANGLE_STEP);
+                            }
+
                             let shift_pressed = running
                                 .framebuffer
                                 .keys_pressed
@@ -533,18 +1227,35 @@ impl ApplicationHandler for App {
                                     .keys_pressed
                                     .contains(&KeyCode::ShiftRight);
 
+                            // Shift picks the human-readable snapshot over the binary one
+                            let quicksave_path = if shift_pressed {
+                                QUICKSAVE_JSON_PATH
+                            } else {
+                                QUICKSAVE_PATH
+                            };
+                            if keycode == KeyCode::F5 {
+                                running.quick_save(quicksave_path);
+                            }
+                            if keycode == KeyCode::F9 {
+                                running.quick_load(quicksave_path);
+                            }
+                            if keycode == KeyCode::F6 {
+                                running.toggle_recording();
+                            }
+
                             if (keycode == KeyCode::Equal && shift_pressed)
                                 || keycode == KeyCode::NumpadAdd
                             {
-                                running.framebuffer.adjust_speed(SPEED_ADJUST_FACTOR);
-                                running.stats_changed = true;
+                                running.change_speed(|framebuffer| {
+                                    framebuffer.adjust_speed(SPEED_ADJUST_FACTOR)
+                                });
                             } else if keycode == KeyCode::Equal {
-                                running.framebuffer.reset_speed();
-                                running.stats_changed = true;
+                                running.change_speed(FrameBuffer::reset_speed);
                             }
                             if keycode == KeyCode::Minus || keycode == KeyCode::NumpadSubtract {
-                                running.framebuffer.adjust_speed(1.0 / SPEED_ADJUST_FACTOR);
-                                running.stats_changed = true;
+                                running.change_speed(|framebuffer| {
+                                    framebuffer.adjust_speed(1.0 / SPEED_ADJUST_FACTOR)
+                                });
                             }
 
                             if matches!(
@@ -564,12 +1275,10 @@ impl ApplicationHandler for App {
                 }
             }
 
-            WindowEvent::RedrawRequested => {
-                // Only draw and present when window is visible
-                // This prevents blocking on present() when window is hidden on Wayland
-                if running.window_visible {
-                    running.draw();
-                }
+            // Only draw and present when window is visible
+            // This prevents blocking on present() when window is hidden on Wayland
+            WindowEvent::RedrawRequested if running.window_visible => {
+                running.draw();
             }
 
             WindowEvent::Occluded(occluded) => {
@@ -585,5 +1294,22 @@ fn main() {
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
     let mut app = App::default();
+
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--seed") {
+        let seed = args.get(i + 1).and_then(|s| s.parse().ok());
+        app.seed = Some(seed.expect("--seed expects an unsigned integer"));
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--replay") {
+        let path = args.get(i + 1).expect("--replay expects a path");
+        match Recording::load(path) {
+            Ok(recording) => app.replay = Some(recording),
+            Err(error) => {
+                eprintln!("Failed to load recording from {}: {}", path, error);
+                std::process::exit(1);
+            }
+        }
+    }
+
     event_loop.run_app(&mut app).unwrap();
 }
@@ -0,0 +1,142 @@
+use crate::color::Color;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// What an asteroid is made of.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Material {
+    Ice,
+    #[default]
+    Rock,
+    Metal,
+}
+
+impl Material {
+    pub const ALL: [Material; 3] = [Material::Ice, Material::Rock, Material::Metal];
+
+    pub fn name(&self) -> &str {
+        match self {
+            Material::Ice => "Ice",
+            Material::Rock => "Rock",
+            Material::Metal => "Metal",
+        }
+    }
+
+    /// Mass per unit area. Rock is π, which gives the radius √m / π bodies always had.
+    pub fn density(&self) -> f32 {
+        PI * self.relative_density()
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Material::Ice => Color::rgb(185, 225, 255),
+            Material::Rock => Color::rgb(225, 215, 200),
+            Material::Metal => Color::rgb(150, 160, 180),
+        }
+    }
+
+    // Density relative to rock
+    fn relative_density(&self) -> f32 {
+        match self {
+            Material::Ice => 0.3,
+            Material::Rock => 1.0,
+            Material::Metal => 2.6,
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Material::Ice => 0,
+            Material::Rock => 1,
+            Material::Metal => 2,
+        }
+    }
+}
+
+/// Share of a body's mass in each material, summing to one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Composition {
+    // Indexed like `Material::ALL`
+    fractions: [f32; 3],
+}
+
+impl Default for Composition {
+    fn default() -> Self {
+        Self::pure(Material::default())
+    }
+}
+
+impl From<Material> for Composition {
+    fn from(material: Material) -> Self {
+        Self::pure(material)
+    }
+}
+
+impl Composition {
+    pub fn pure(material: Material) -> Self {
+        let mut fractions = [0.0; 3];
+        fractions[material.index()] = 1.0;
+        Self { fractions }
+    }
+
+    /// Mass-weighted mixture of `(composition, mass)` parts, summed in the order given.
+    pub fn mix(parts: &[(Composition, f32)]) -> Self {
+        let total_mass: f32 = parts.iter().map(|&(_, mass)| mass).sum();
+        if total_mass <= 0.0 {
+            return Self::default();
+        }
+        let mut fractions = [0.0; 3];
+        for (composition, mass) in parts {
+            for (fraction, part) in fractions.iter_mut().zip(composition.fractions) {
+                *fraction += part * mass;
+            }
+        }
+        Self {
+            fractions: fractions.map(|fraction| fraction / total_mass),
+        }
+    }
+
+    pub fn fraction(&self, material: Material) -> f32 {
+        self.fractions[material.index()]
+    }
+
+    /// The material with the largest share, the denser one on a tie.
+    pub fn dominant(&self) -> Material {
+        Material::ALL
+            .into_iter()
+            .max_by(|a, b| self.fraction(*a).total_cmp(&self.fraction(*b)))
+            .unwrap_or_default()
+    }
+
+    /// Density of the mixture, where each material keeps its own area.
+    pub fn density(&self) -> f32 {
+        Material::Rock.density() / self.area_per_rock_area()
+    }
+
+    /// Radius relative to a rock body of the same mass, exactly one for pure rock.
+    pub fn radius_scale(&self) -> f32 {
+        self.area_per_rock_area().sqrt()
+    }
+
+    /// Mass-weighted blend of the material colours.
+    pub fn color(&self) -> Color {
+        let mut channels = [0.0f32; 3];
+        for material in Material::ALL {
+            let color = material.color();
+            let fraction = self.fraction(material);
+            channels[0] += color.r as f32 * fraction;
+            channels[1] += color.g as f32 * fraction;
+            channels[2] += color.b as f32 * fraction;
+        }
+        let [r, g, b] = channels.map(|channel| channel.round().clamp(0.0, 255.0) as u8);
+        Color::rgb(r, g, b)
+    }
+
+    // Area of the body over the area it would cover as pure rock
+    fn area_per_rock_area(&self) -> f32 {
+        Material::ALL
+            .into_iter()
+            .map(|material| self.fraction(material) / material.relative_density())
+            .sum()
+    }
+}
@@ -0,0 +1,84 @@
+use glam::{Vec2, vec2};
+use serde::{Deserialize, Serialize};
+
+/// Prescribed movement for an asteroid that gravity and collisions don't push around or spin.
+/// Constrained bodies still attract everything else and can absorb other bodies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Motion {
+    /// Held at `pos` for good.
+    Pinned { pos: Vec2 },
+    /// Circular orbit around `center`, at angle `phase + angular_velocity * t` at world time `t`,
+    /// counter-clockwise for positive `angular_velocity`.
+    Orbit {
+        center: Vec2,
+        radius: f32,
+        angular_velocity: f32,
+        phase: f32,
+    },
+    /// Straight lines between `(world_time, position)` keyframes in time order. The body rests
+    /// at the ends unless `looped`, then the path repeats with the period of its last keyframe.
+    Path {
+        keyframes: Vec<(f32, Vec2)>,
+        looped: bool,
+    },
+}
+
+impl Motion {
+    /// The circular orbit around `center` that passes through `pos` at world time `time`.
+    pub fn orbit_through(center: Vec2, pos: Vec2, angular_velocity: f32, time: f32) -> Self {
+        let offset = pos - center;
+        Motion::Orbit {
+            center,
+            radius: offset.length(),
+            angular_velocity,
+            phase: offset.y.atan2(offset.x) - angular_velocity * time,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Motion::Pinned { .. } => "Pinned",
+            Motion::Orbit { .. } => "Orbit",
+            Motion::Path { .. } => "Path",
+        }
+    }
+
+    /// Position and velocity at world time `time`, `None` for a path without keyframes.
+    pub fn state_at(&self, time: f32) -> Option<(Vec2, Vec2)> {
+        match self {
+            Motion::Pinned { pos } => Some((*pos, Vec2::ZERO)),
+            Motion::Orbit {
+                center,
+                radius,
+                angular_velocity,
+                phase,
+            } => {
+                let angle = phase + angular_velocity * time;
+                let direction = vec2(angle.cos(), angle.sin());
+                Some((
+                    *center + direction * *radius,
+                    direction.perp() * *radius * *angular_velocity,
+                ))
+            }
+            Motion::Path { keyframes, looped } => {
+                let (&(first_time, first), &(last_time, last)) =
+                    (keyframes.first()?, keyframes.last()?);
+                let time = if *looped && last_time > first_time {
+                    first_time + (time - first_time).rem_euclid(last_time - first_time)
+                } else {
+                    time
+                };
+                if time <= first_time {
+                    return Some((first, Vec2::ZERO));
+                }
+
+                let Some(segment) = keyframes.windows(2).find(|pair| time < pair[1].0) else {
+                    return Some((last, Vec2::ZERO));
+                };
+                let ((t0, p0), (t1, p1)) = (segment[0], segment[1]);
+                let vel = (p1 - p0) / (t1 - t0);
+                Some((p0 + vel * (time - t0), vel))
+            }
+        }
+    }
+}
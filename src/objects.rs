@@ -1,18 +1,56 @@
+use crate::boundary::minimum_image;
 use crate::color::Color;
-use glam::{vec2, Vec2};
-use std::f32::consts::PI;
+use crate::gravity::Gravity;
+use crate::material::Composition;
+use glam::{Vec2, vec2};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{PI, TAU};
 
-#[derive(Default, Clone, Copy, Debug)]
+// Bodies smaller than this on screen, in pixels, don't show their spin marker
+const SPIN_MARKER_MIN_RADIUS: f32 = 3.0;
+
+#[derive(Default, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Asteroid {
+    // Unique within a world, zero until the world assigns one
+    id: u64,
     pos: Vec2,
     vel: Vec2,
     size: f32,
+    // Angular velocity in radians per second, counter-clockwise positive
+    spin: f32,
+    // Orientation in radians, only tracked to show the spin
+    angle: f32,
+    composition: Composition,
 }
 
 impl Asteroid {
     pub fn new(pos: Vec2, vel: Vec2, size: f32) -> Self {
-        Self { pos, vel, size }
+        Self {
+            id: 0,
+            pos,
+            vel,
+            size,
+            spin: 0.0,
+            angle: 0.0,
+            composition: Composition::default(),
+        }
+    }
+
+    /// A body of the given composition, `new` makes pure rock.
+    pub fn with_composition(mut self, composition: impl Into<Composition>) -> Self {
+        self.composition = composition.into();
+        self
+    }
+
+    /// Stable identity assigned by `WorldState`, kept for the whole life of the body.
+    pub fn id(&self) -> u64 {
+        self.id
     }
+
+    pub(crate) fn set_id(&mut self, id: u64) {
+        self.id = id;
+    }
+
     pub fn pos(&self) -> Vec2 {
         self.pos
     }
@@ -29,32 +67,105 @@ impl Asteroid {
         self.pos = pos;
     }
 
+    /// Radius of a disc of this mass at the body's density.
     pub fn radius(self) -> f32 {
-        self.size.sqrt() / PI
+        self.size.sqrt() / PI * self.composition.radius_scale()
+    }
+
+    pub fn composition(&self) -> Composition {
+        self.composition
+    }
+
+    pub fn density(&self) -> f32 {
+        self.composition.density()
     }
 
     pub fn size(self) -> f32 {
         self.size
     }
 
+    /// Angular velocity in radians per second, counter-clockwise positive.
+    pub fn spin(&self) -> f32 {
+        self.spin
+    }
+
+    pub fn set_spin(&mut self, spin: f32) {
+        self.spin = spin;
+    }
+
+    pub fn angle(&self) -> f32 {
+        self.angle
+    }
+
+    /// Turns the body by its spin over `dt`.
+    pub fn rotate(&mut self, dt: f32) {
+        self.angle = (self.angle + self.spin * dt).rem_euclid(TAU);
+    }
+
+    /// Moment of inertia of a uniform disc, ½mr².
+    pub fn moment_of_inertia(&self) -> f32 {
+        0.5 * self.size * self.radius() * self.radius()
+    }
+
+    /// Angular momentum of the spin alone, about the body's own centre.
+    pub fn spin_angular_momentum(&self) -> f32 {
+        self.moment_of_inertia() * self.spin
+    }
+
+    /// Velocity of the point at `offset` from the centre, moving with the body and its spin.
+    pub fn surface_velocity(&self, offset: Vec2) -> Vec2 {
+        self.vel + offset.perp() * self.spin
+    }
+
+    /// Applies `impulse` at `offset` from the centre, changing both velocity and spin.
+    pub fn apply_impulse(&mut self, impulse: Vec2, offset: Vec2) {
+        self.vel += impulse / self.size;
+        self.spin += offset.perp_dot(impulse) / self.moment_of_inertia();
+    }
+
+    /// Filled disc with a line from the centre to the rim that turns with the body.
     pub fn draw(&self, fb: &mut crate::framebuffer::FrameBuffer, color: Color) {
         fb.draw_circle(self.pos, self.radius(), color);
+        if self.radius() * fb.zoom >= SPIN_MARKER_MIN_RADIUS {
+            let rim = self.pos + vec2(self.angle.cos(), self.angle.sin()) * self.radius();
+            fb.draw_world_line(self.pos, rim, Color::BLACK);
+        }
+    }
+
+    /// Acceleration `other` exerts on this asteroid.
+    /// Without softening, overlapping bodies don't attract each other, they are about to merge anyway.
+    pub fn acceleration_towards(&self, other: &Asteroid, gravity: &Gravity) -> Vec2 {
+        self.acceleration_towards_nearest(other, gravity, None)
     }
 
-    pub fn update(&mut self, others: &Vec<Asteroid>, step: f32) {
+    /// Like `acceleration_towards`, pulled towards the nearest image of `other`
+    /// in a periodic domain of size `period`.
+    pub fn acceleration_towards_nearest(
+        &self,
+        other: &Asteroid,
+        gravity: &Gravity,
+        period: Option<Vec2>,
+    ) -> Vec2 {
+        let direction = minimum_image(other.pos - self.pos, period);
+        let distance = direction.length();
+        if !gravity.applies(distance, self.radius() + other.radius()) {
+            return Vec2::ZERO;
+        }
+        gravity.acceleration(direction, other.size)
+    }
+
+    /// Brute-force sum of accelerations from every other asteroid, see `acceleration_towards_nearest`.
+    pub fn acceleration_from(
+        &self,
+        others: &[Asteroid],
+        gravity: &Gravity,
+        period: Option<Vec2>,
+    ) -> Vec2 {
         let mut acc = vec2(0.0, 0.0);
         for asteroid in others {
-            let direction = asteroid.pos - self.pos;
-            let distance = direction.length();
-            if distance < (self.radius() + asteroid.radius()) {
-                continue;
-            }
-            let force_magnitude = asteroid.size / distance;
-            let force = direction.normalize() * force_magnitude;
-            acc += force;
+            acc += self.acceleration_towards_nearest(asteroid, gravity, period);
         }
-        self.vel = acc * step + self.vel;
-        self.pos += self.vel * step;
+        acc
     }
 
     pub fn collides_with(&self, other: &Asteroid) -> bool {
@@ -62,22 +173,32 @@ impl Asteroid {
     }
 
     pub fn merge_with(&self, other: &Asteroid) -> Asteroid {
-        let mass1 = self.size;
-        let mass2 = other.size;
-        let total_mass = mass1 + mass2;
-
-        let momentum_x = mass1 * self.vel.x + mass2 * other.vel.x;
-        let momentum_y = mass1 * self.vel.y + mass2 * other.vel.y;
-
-        let new_vel = vec2(momentum_x / total_mass, momentum_y / total_mass);
-
-        let new_size = self.size + other.size;
-
-        let new_pos = vec2(
-            (mass1 * self.pos.x + mass2 * other.pos.x) / total_mass,
-            (mass1 * self.pos.y + mass2 * other.pos.y) / total_mass,
-        );
+        Asteroid::merge_all(&[*self, *other])
+    }
 
-        Asteroid::new(new_pos, new_vel, new_size)
+    /// One body with the combined mass, momentum and centre of mass of `bodies`, summed in
+    /// the order given. Their spins and their orbits around the common centre of mass all
+    /// go into the spin of the result, and their materials mix by mass.
+    pub fn merge_all(bodies: &[Asteroid]) -> Asteroid {
+        let mut mass = 0.0;
+        let mut momentum = Vec2::ZERO;
+        let mut weighted_pos = Vec2::ZERO;
+        for body in bodies {
+            mass += body.size;
+            momentum += body.vel * body.size;
+            weighted_pos += body.pos * body.size;
+        }
+        let parts: Vec<(Composition, f32)> =
+            bodies.iter().map(|b| (b.composition, b.size)).collect();
+        let mut merged = Asteroid::new(weighted_pos / mass, momentum / mass, mass)
+            .with_composition(Composition::mix(&parts));
+
+        let mut angular_momentum = 0.0;
+        for body in bodies {
+            angular_momentum += body.spin_angular_momentum()
+                + body.size * (body.pos - merged.pos).perp_dot(body.vel - merged.vel);
+        }
+        merged.spin = angular_momentum / merged.moment_of_inertia();
+        merged
     }
 }
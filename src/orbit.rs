@@ -0,0 +1,320 @@
+use crate::gravity::{ForceLaw, Gravity};
+use crate::objects::Asteroid;
+use crate::world::WorldState;
+use glam::Vec2;
+use std::f64::consts::PI;
+
+// Samples for the radial integrals of non-Keplerian orbits
+const INTEGRATION_STEPS: usize = 512;
+// Bisection rounds when looking for turning points, plenty for f32 results
+const BISECTION_STEPS: usize = 60;
+// Apoapsis search gives up this many radii out and calls the orbit unbound
+const MAX_APOAPSIS_FACTOR: f64 = 1.0e6;
+
+/// Shape of an orbit around a dominant body.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitalElements {
+    /// ID of the body being orbited.
+    pub primary: u64,
+    /// Mean of periapsis and apoapsis. Negative for Newtonian hyperbolic orbits,
+    /// infinite for other unbound ones.
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub periapsis: f32,
+    /// `None` for unbound orbits.
+    pub apoapsis: Option<f32>,
+    /// Time from periapsis to periapsis, `None` for unbound orbits.
+    /// Orbits under non-Newtonian laws precess, so this is the radial period.
+    pub period: Option<f32>,
+    /// Direction of periapsis from the primary, counter-clockwise from +x in radians.
+    pub argument_of_periapsis: f32,
+}
+
+/// The body whose gravity dominates at `pos` for an orbiter of `mass`.
+///
+/// Bodies heavier than the orbiter are sorted into a hierarchy: each one belongs to the
+/// lightest heavier body whose Hill sphere contains it, with the heaviest body's sphere
+/// unbounded. The orbiter then belongs to the lightest body whose sphere contains it.
+pub fn dominant_body(
+    world: &WorldState,
+    pos: Vec2,
+    mass: f32,
+    exclude: Option<u64>,
+) -> Option<&Asteroid> {
+    let spheres = hill_spheres(world, |a| a.size() > mass && Some(a.id()) != exclude);
+    innermost(world, &spheres, pos).map(|index| spheres[index].body)
+}
+
+/// One body of the Hill hierarchy, see `hill_hierarchy`.
+#[derive(Debug, Clone, Copy)]
+pub struct HillSphere<'a> {
+    pub body: &'a Asteroid,
+    /// Position of `body` in `world.asteroids`.
+    pub index: usize,
+    /// Infinite for the heaviest body.
+    pub radius: f32,
+    /// Index of the body this one orbits, `None` for the heaviest body.
+    pub primary: Option<usize>,
+}
+
+/// Every asteroid with its Hill sphere and primary, heaviest first, as used by `dominant_body`.
+pub fn hill_hierarchy(world: &WorldState) -> Vec<HillSphere<'_>> {
+    hill_spheres(world, |_| true)
+}
+
+fn hill_spheres<'a>(
+    world: &'a WorldState,
+    include: impl Fn(&Asteroid) -> bool,
+) -> Vec<HillSphere<'a>> {
+    let boundary = world.boundary();
+    let exponent = world.gravity().law.exponent();
+
+    let mut candidates: Vec<(usize, &Asteroid)> = world
+        .asteroids
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| include(a))
+        .collect();
+    candidates.sort_by(|(_, a), (_, b)| b.size().total_cmp(&a.size()).then(a.id().cmp(&b.id())));
+
+    // Heaviest first, so every body's primary already has its sphere
+    let mut spheres: Vec<HillSphere> = Vec::with_capacity(candidates.len());
+    for (index, body) in candidates {
+        let primary = innermost(world, &spheres, body.pos());
+        let radius = match primary {
+            Some(primary) => {
+                let primary = spheres[primary].body;
+                let distance = boundary.separation(primary.pos(), body.pos()).length();
+                hill_radius(distance, body.size(), primary.size(), exponent)
+            }
+            None => f32::INFINITY,
+        };
+        spheres.push(HillSphere {
+            body,
+            index,
+            radius,
+            primary,
+        });
+    }
+    spheres
+}
+
+// The lightest body whose sphere contains `pos`
+fn innermost(world: &WorldState, spheres: &[HillSphere], pos: Vec2) -> Option<usize> {
+    let boundary = world.boundary();
+    spheres
+        .iter()
+        .rposition(|sphere| boundary.separation(sphere.body.pos(), pos).length() < sphere.radius)
+}
+
+/// Radius inside which a body of `mass` at `distance` from a `primary_mass` dominates,
+/// where its own pull matches the primary's tidal and centrifugal pull.
+/// For a force falling off as r^-k this is d * (m / ((k + 1) * M))^(1 / (k + 1)).
+pub fn hill_radius(distance: f32, mass: f32, primary_mass: f32, exponent: f32) -> f32 {
+    distance * (mass / ((exponent + 1.0) * primary_mass)).powf(1.0 / (exponent + 1.0))
+}
+
+/// Elements of the ship's orbit around its dominant body.
+pub fn ship_orbit(world: &WorldState) -> Option<OrbitalElements> {
+    let ship = &world.ship;
+    let primary = dominant_body(world, ship.pos, ship.mass(), None)?;
+    let offset = world.boundary().separation(primary.pos(), ship.pos);
+    orbital_elements(
+        &world.gravity(),
+        primary,
+        offset,
+        ship.vel - primary.vel(),
+        ship.mass(),
+    )
+}
+
+/// Elements of an asteroid's orbit around its dominant body, `None` for unknown IDs
+/// and for the heaviest body.
+pub fn asteroid_orbit(world: &WorldState, id: u64) -> Option<OrbitalElements> {
+    let body = world.asteroid(id)?;
+    let primary = dominant_body(world, body.pos(), body.size(), Some(id))?;
+    let offset = world.boundary().separation(primary.pos(), body.pos());
+    orbital_elements(
+        &world.gravity(),
+        primary,
+        offset,
+        body.vel() - primary.vel(),
+        body.size(),
+    )
+}
+
+/// Elements of a two-body orbit with relative position `offset` and velocity `vel`.
+/// Exact for unsoftened Newtonian gravity, found from the turning points of the
+/// effective potential for every other law. `None` for degenerate orbits.
+pub fn orbital_elements(
+    gravity: &Gravity,
+    primary: &Asteroid,
+    offset: Vec2,
+    vel: Vec2,
+    mass: f32,
+) -> Option<OrbitalElements> {
+    let total_mass = primary.size() + mass;
+    if offset.length() <= 0.0 || total_mass <= 0.0 {
+        return None;
+    }
+
+    let shape = if gravity.law == ForceLaw::Newtonian && gravity.softening == 0.0 {
+        kepler(gravity.constant * total_mass, offset, vel)
+    } else {
+        turning_points(gravity, total_mass, offset, vel)
+    }?;
+
+    Some(OrbitalElements {
+        primary: primary.id(),
+        ..shape
+    })
+}
+
+fn kepler(mu: f32, offset: Vec2, vel: Vec2) -> Option<OrbitalElements> {
+    let (mu, r, v) = (mu as f64, offset.as_dvec2(), vel.as_dvec2());
+    let energy = 0.5 * v.length_squared() - mu / r.length();
+    let ecc = ((v.length_squared() - mu / r.length()) * r - r.dot(v) * v) / mu;
+    let eccentricity = ecc.length();
+    let angular_momentum = r.perp_dot(v);
+    if angular_momentum == 0.0 {
+        return None;
+    }
+
+    // Circular orbits have no periapsis of their own, use the current position
+    let direction = if eccentricity > 1e-6 { ecc } else { r };
+    let semi_latus_rectum = angular_momentum * angular_momentum / mu;
+    let bound = energy < 0.0;
+    let semi_major_axis = -mu / (2.0 * energy);
+
+    Some(OrbitalElements {
+        primary: 0,
+        semi_major_axis: semi_major_axis as f32,
+        eccentricity: eccentricity as f32,
+        periapsis: (semi_latus_rectum / (1.0 + eccentricity)) as f32,
+        apoapsis: bound.then_some((semi_major_axis * (1.0 + eccentricity)) as f32),
+        period: bound.then(|| (2.0 * PI * (semi_major_axis.powi(3) / mu).sqrt()) as f32),
+        argument_of_periapsis: direction.y.atan2(direction.x) as f32,
+    })
+}
+
+fn turning_points(
+    gravity: &Gravity,
+    total_mass: f32,
+    offset: Vec2,
+    vel: Vec2,
+) -> Option<OrbitalElements> {
+    let (r, v) = (offset.as_dvec2(), vel.as_dvec2());
+    let radius = r.length();
+    let angular_momentum = r.perp_dot(v);
+    if angular_momentum == 0.0 {
+        return None;
+    }
+    // `Gravity::potential` in double precision, near-circular orbits need it
+    let (constant, softening) = (gravity.constant as f64, gravity.softening as f64);
+    let potential = |distance: f64| {
+        let softened = distance * distance + softening * softening;
+        match gravity.law {
+            ForceLaw::Logarithmic => 0.5 * constant * total_mass as f64 * softened.ln(),
+            ForceLaw::Newtonian => -constant * total_mass as f64 / softened.sqrt(),
+        }
+    };
+    let energy = 0.5 * v.length_squared() + potential(radius);
+
+    // Twice the radial kinetic energy per unit mass, non-negative between the turning points
+    let radial = |distance: f64| {
+        2.0 * (energy - potential(distance))
+            - angular_momentum * angular_momentum / (distance * distance)
+    };
+    let bisect = |mut inside: f64, mut outside: f64| {
+        for _ in 0..BISECTION_STEPS {
+            let middle = 0.5 * (inside + outside);
+            if radial(middle) >= 0.0 {
+                inside = middle;
+            } else {
+                outside = middle;
+            }
+        }
+        inside
+    };
+
+    let periapsis = bisect(radius, 0.0);
+    let mut outside = radius * 2.0;
+    while radial(outside) >= 0.0 {
+        outside *= 2.0;
+        if outside > radius * MAX_APOAPSIS_FACTOR {
+            return unbound(periapsis, r);
+        }
+    }
+    let apoapsis = bisect(radius, outside);
+
+    // r = mid - half * cos(theta) removes the inverse square root singularities at the ends
+    let (mid, half) = (0.5 * (apoapsis + periapsis), 0.5 * (apoapsis - periapsis));
+    let current_theta = ((mid - radius) / half.max(f64::MIN_POSITIVE))
+        .clamp(-1.0, 1.0)
+        .acos();
+    let integrate = |upper: f64, integrand: &dyn Fn(f64) -> f64| {
+        let step = upper / INTEGRATION_STEPS as f64;
+        (0..INTEGRATION_STEPS)
+            .map(|k| {
+                let theta = (k as f64 + 0.5) * step;
+                let distance = mid - half * theta.cos();
+                let speed = radial(distance).max(0.0).sqrt();
+                if speed > 0.0 {
+                    integrand(distance) * half * theta.sin() / speed * step
+                } else {
+                    0.0
+                }
+            })
+            .sum::<f64>()
+    };
+
+    let circular = half <= radius * 1e-5;
+    let period = if circular {
+        2.0 * PI * radius * radius / angular_momentum.abs()
+    } else {
+        2.0 * integrate(PI, &|_| 1.0)
+    };
+
+    // Angle swept since periapsis, or still to go if the body is falling back in
+    let swept = if circular {
+        0.0
+    } else {
+        integrate(current_theta, &|distance| {
+            angular_momentum.abs() / (distance * distance)
+        })
+    };
+    let outward = r.dot(v) >= 0.0;
+    let turn = if outward == (angular_momentum > 0.0) {
+        -swept
+    } else {
+        swept
+    };
+    let argument_of_periapsis = r.y.atan2(r.x) + turn;
+
+    Some(OrbitalElements {
+        primary: 0,
+        semi_major_axis: mid as f32,
+        eccentricity: (half / mid) as f32,
+        periapsis: periapsis as f32,
+        apoapsis: Some(apoapsis as f32),
+        period: Some(period as f32),
+        argument_of_periapsis: wrap_angle(argument_of_periapsis) as f32,
+    })
+}
+
+// Escaping bodies only get their closest approach, the rest is meaningless
+fn unbound(periapsis: f64, offset: glam::DVec2) -> Option<OrbitalElements> {
+    Some(OrbitalElements {
+        primary: 0,
+        semi_major_axis: f32::INFINITY,
+        eccentricity: 1.0,
+        periapsis: periapsis as f32,
+        apoapsis: None,
+        period: None,
+        argument_of_periapsis: offset.y.atan2(offset.x) as f32,
+    })
+}
+
+fn wrap_angle(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
@@ -0,0 +1,228 @@
+use crate::gravity::{DEFAULT_OPENING_ANGLE, GravitySolver};
+use crate::objects::Asteroid;
+use crate::quadtree::QuadTree;
+use crate::world::WorldState;
+use glam::Vec2;
+
+/// How far ahead and in how much detail `predict_ship_path` looks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PredictionSettings {
+    /// World time covered by the prediction.
+    pub horizon: f32,
+    pub step: f32,
+    /// Long horizons take longer steps so the prediction never takes more than this many.
+    pub max_steps: usize,
+    /// How many of the asteroids pulling hardest on the ship move during the prediction,
+    /// the rest stay put.
+    pub moving_bodies: usize,
+    /// Surface distance under which the closest pass by an asteroid is reported.
+    pub approach_distance: f32,
+}
+
+impl Default for PredictionSettings {
+    fn default() -> Self {
+        Self {
+            horizon: 30.0,
+            step: 0.05,
+            max_steps: 1000,
+            moving_bodies: 8,
+            approach_distance: 100.0,
+        }
+    }
+}
+
+/// Closest point of the predicted path to one asteroid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Approach {
+    pub asteroid: u64,
+    /// World time from now.
+    pub time: f32,
+    pub ship_pos: Vec2,
+    /// Gap between the surfaces, zero or less for a collision.
+    pub distance: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShipPrediction {
+    /// Ship positions one step apart, starting at its current position.
+    pub path: Vec<Vec2>,
+    /// Where the path ends early because the ship hits an asteroid.
+    pub collision: Option<Approach>,
+    /// Close passes within `PredictionSettings::approach_distance`, in time order.
+    pub approaches: Vec<Approach>,
+}
+
+/// Forward-integrates the ship with its engine cut under the current asteroid field.
+/// The world itself isn't touched; merges and the ship's own pull are ignored.
+/// Bodies that stay put are summed through one Barnes-Hut tree, using the world's opening
+/// angle or the default one under brute force. The same tree finds the ones near the ship.
+pub fn predict_ship_path(world: &WorldState, settings: &PredictionSettings) -> ShipPrediction {
+    let gravity = world.gravity();
+    let boundary = world.boundary();
+    let period = boundary.period();
+    let ship_radius = world.ship.radius();
+    let (mut pos, mut vel) = (world.ship.pos, world.ship.vel);
+
+    // The near and heavy bodies steer the ship the most, so they are the ones worth moving
+    let pulls: Vec<f32> = world
+        .asteroids
+        .iter()
+        .map(|body| {
+            gravity
+                .acceleration(boundary.separation(pos, body.pos()), body.size())
+                .length()
+        })
+        .collect();
+    let mut ranked: Vec<usize> = (0..world.asteroids.len()).collect();
+    ranked.sort_by(|&a, &b| pulls[b].total_cmp(&pulls[a]));
+    let mut is_moving = vec![false; world.asteroids.len()];
+    for &i in ranked.iter().take(settings.moving_bodies) {
+        is_moving[i] = true;
+    }
+    let (mut moving, mut fixed) = (Vec::new(), Vec::new());
+    for (body, &is_moving) in world.asteroids.iter().zip(&is_moving) {
+        if is_moving {
+            moving.push(*body);
+        } else {
+            fixed.push(*body);
+        }
+    }
+
+    // Bodies that stay put are summed through one tree for the whole prediction
+    let tree = QuadTree::new(&fixed);
+    let theta = match world.gravity_solver() {
+        GravitySolver::BarnesHut { theta } => theta,
+        GravitySolver::BruteForce => DEFAULT_OPENING_ANGLE,
+    };
+    let field = |pos: Vec2, radius: f32, moving: &[Asteroid]| {
+        let mut acc = tree.acceleration_at(&fixed, pos, radius, theta, &gravity, period);
+        for body in moving {
+            let direction = boundary.separation(pos, body.pos());
+            if gravity.applies(direction.length(), radius + body.radius()) {
+                acc += gravity.acceleration(direction, body.size());
+            }
+        }
+        acc
+    };
+
+    let mut prediction = ShipPrediction {
+        path: vec![pos],
+        ..Default::default()
+    };
+    // Fixed bodies first, then the moving ones
+    let mut closest: Vec<Option<Approach>> = vec![None; fixed.len() + moving.len()];
+    // Only bodies this close to the ship's centre can be hit or passed closely
+    let reach = ship_radius + settings.approach_distance.max(0.0);
+
+    // Semi-implicit Euler is plenty for a guide and stays stable on long horizons
+    let dt = settings
+        .step
+        .max(settings.horizon / settings.max_steps.max(1) as f32);
+    let steps = (settings.horizon / dt).ceil() as usize;
+    for step in 1..=steps {
+        let time = step as f32 * dt;
+
+        let body_acc: Vec<Vec2> = moving
+            .iter()
+            .map(|body| field(body.pos(), body.radius(), &moving))
+            .collect();
+        for (body, acc) in moving.iter_mut().zip(body_acc) {
+            body.set_vel(body.vel() + acc * dt);
+            body.set_pos(body.pos() + body.vel() * dt);
+        }
+
+        vel += field(pos, ship_radius, &moving) * dt;
+        pos += vel * dt;
+        prediction.path.push(pos);
+
+        let near = tree.within(&fixed, pos, reach, period);
+        let candidates = near.into_iter().map(|i| (i, &fixed[i])).chain(
+            moving
+                .iter()
+                .enumerate()
+                .map(|(m, body)| (fixed.len() + m, body)),
+        );
+        let mut hit: Option<Approach> = None;
+        for (index, body) in candidates {
+            let distance =
+                boundary.separation(pos, body.pos()).length() - ship_radius - body.radius();
+            let approach = Approach {
+                asteroid: body.id(),
+                time,
+                ship_pos: pos,
+                distance,
+            };
+            if closest[index].is_none_or(|closest| distance < closest.distance) {
+                closest[index] = Some(approach);
+            }
+            if distance <= 0.0 && hit.is_none_or(|hit| distance < hit.distance) {
+                hit = Some(approach);
+            }
+        }
+        if hit.is_some() {
+            prediction.collision = hit;
+            break;
+        }
+    }
+
+    prediction.approaches = closest
+        .into_iter()
+        .flatten()
+        .filter(|approach| {
+            approach.distance > 0.0 && approach.distance < settings.approach_distance
+        })
+        .collect();
+    prediction
+        .approaches
+        .sort_by(|a, b| a.time.total_cmp(&b.time));
+    prediction
+}
+
+impl ShipPrediction {
+    /// Ghost path with a ring at every close pass and a cross where the ship would hit.
+    pub fn draw(&self, fb: &mut crate::framebuffer::FrameBuffer, ship_radius: f32) {
+        use crate::color::Color;
+        use glam::vec2;
+
+        let path_color = Color {
+            r: 80,
+            g: 160,
+            b: 200,
+            a: 160,
+        };
+        for segment in self.path.windows(2) {
+            fb.draw_world_line(segment[0], segment[1], path_color);
+        }
+
+        let approach_color = Color {
+            r: 230,
+            g: 200,
+            b: 60,
+            a: 120,
+        };
+        for approach in &self.approaches {
+            fb.draw_circle(approach.ship_pos, ship_radius, approach_color);
+        }
+
+        if let Some(collision) = &self.collision {
+            let collision_color = Color {
+                r: 255,
+                g: 60,
+                b: 60,
+                a: 255,
+            };
+            let center = fb.world_to_screen(collision.ship_pos);
+            let arm = 6.0;
+            fb.draw_screen_line(
+                center - vec2(arm, arm),
+                center + vec2(arm, arm),
+                collision_color,
+            );
+            fb.draw_screen_line(
+                center - vec2(arm, -arm),
+                center + vec2(arm, -arm),
+                collision_color,
+            );
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use crate::boundary::minimum_image;
+use crate::gravity::Gravity;
+use crate::objects::Asteroid;
+use glam::{Vec2, vec2};
+use std::ops::Range;
+
+const LEAF_CAPACITY: usize = 8;
+const MAX_DEPTH: u32 = 32;
+const NO_CHILD: usize = usize::MAX;
+
+struct Node {
+    center: Vec2,
+    half_size: f32,
+    mass: f32,
+    center_of_mass: Vec2,
+    // Largest radius of any body inside
+    max_radius: f32,
+    children: [usize; 4],
+    // Range into `QuadTree::order`, only used by leaves
+    bodies: Range<usize>,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.children == [NO_CHILD; 4]
+    }
+
+    fn contains(&self, pos: Vec2) -> bool {
+        (pos - self.center).abs().max_element() <= self.half_size
+    }
+
+    // Members of cells at least half the period wide, or reaching past half a period from
+    // `pos`, are nearest to it through different images
+    fn spans_images(&self, pos: Vec2, period: Option<Vec2>) -> bool {
+        period.is_some_and(|period| {
+            let offset = minimum_image(self.center - pos, Some(period)).abs();
+            self.half_size * 2.0 >= period.min_element() / 2.0
+                || (offset + self.half_size).cmpge(period / 2.0).any()
+        })
+    }
+}
+
+/// Mass-aggregating quadtree over asteroid positions for Barnes-Hut gravity.
+pub struct QuadTree {
+    nodes: Vec<Node>,
+    // Asteroid indices, permuted so every leaf owns a contiguous range
+    order: Vec<usize>,
+}
+
+impl QuadTree {
+    pub fn new(asteroids: &[Asteroid]) -> Self {
+        let mut tree = Self {
+            nodes: Vec::new(),
+            order: (0..asteroids.len()).collect(),
+        };
+        if asteroids.is_empty() {
+            return tree;
+        }
+
+        let mut min = vec2(f32::INFINITY, f32::INFINITY);
+        let mut max = vec2(f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for asteroid in asteroids {
+            min = min.min(asteroid.pos());
+            max = max.max(asteroid.pos());
+        }
+        let center = (min + max) / 2.0;
+        let half_size = ((max - min).max_element() / 2.0).max(1.0);
+
+        tree.build(asteroids, 0..asteroids.len(), center, half_size, 0);
+        tree
+    }
+
+    fn build(
+        &mut self,
+        asteroids: &[Asteroid],
+        range: Range<usize>,
+        center: Vec2,
+        half_size: f32,
+        depth: u32,
+    ) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            center,
+            half_size,
+            mass: 0.0,
+            center_of_mass: center,
+            max_radius: 0.0,
+            children: [NO_CHILD; 4],
+            bodies: range.clone(),
+        });
+
+        if range.len() <= LEAF_CAPACITY || depth >= MAX_DEPTH {
+            let mut mass = 0.0;
+            let mut weighted_pos = vec2(0.0, 0.0);
+            let mut max_radius: f32 = 0.0;
+            for &i in &self.order[range] {
+                mass += asteroids[i].size();
+                weighted_pos += asteroids[i].pos() * asteroids[i].size();
+                max_radius = max_radius.max(asteroids[i].radius());
+            }
+            let node = &mut self.nodes[index];
+            node.mass = mass;
+            node.max_radius = max_radius;
+            if mass > 0.0 {
+                node.center_of_mass = weighted_pos / mass;
+            }
+            return index;
+        }
+
+        // Split into quadrants: first by y, then each half by x
+        let order = &mut self.order[range.clone()];
+        let y_split = partition(order, |i| asteroids[i].pos().y < center.y);
+        let (bottom, top) = order.split_at_mut(y_split);
+        let bottom_split = partition(bottom, |i| asteroids[i].pos().x < center.x);
+        let top_split = partition(top, |i| asteroids[i].pos().x < center.x);
+
+        let start = range.start;
+        let quadrants = [
+            (start..start + bottom_split, vec2(-1.0, -1.0)),
+            (start + bottom_split..start + y_split, vec2(1.0, -1.0)),
+            (
+                start + y_split..start + y_split + top_split,
+                vec2(-1.0, 1.0),
+            ),
+            (start + y_split + top_split..range.end, vec2(1.0, 1.0)),
+        ];
+
+        let child_half = half_size / 2.0;
+        let mut mass = 0.0;
+        let mut weighted_pos = vec2(0.0, 0.0);
+        let mut max_radius: f32 = 0.0;
+        for (quadrant, (child_range, offset)) in quadrants.into_iter().enumerate() {
+            if child_range.is_empty() {
+                continue;
+            }
+            let child = self.build(
+                asteroids,
+                child_range,
+                center + offset * child_half,
+                child_half,
+                depth + 1,
+            );
+            mass += self.nodes[child].mass;
+            weighted_pos += self.nodes[child].center_of_mass * self.nodes[child].mass;
+            max_radius = max_radius.max(self.nodes[child].max_radius);
+            self.nodes[index].children[quadrant] = child;
+        }
+
+        let node = &mut self.nodes[index];
+        node.mass = mass;
+        node.max_radius = max_radius;
+        if mass > 0.0 {
+            node.center_of_mass = weighted_pos / mass;
+        }
+        index
+    }
+
+    /// Approximate acceleration on `asteroids[index]` from every other asteroid in the tree.
+    /// `asteroids` must be the same slice the tree was built from.
+    /// With a `period`, cells pull from the nearest image of their centre of mass. Cells at least
+    /// half the period across, or reaching past half a period from the target, are always
+    /// opened whatever `theta`, since their members needn't share that image.
+    pub fn acceleration(
+        &self,
+        asteroids: &[Asteroid],
+        index: usize,
+        theta: f32,
+        gravity: &Gravity,
+        period: Option<Vec2>,
+    ) -> Vec2 {
+        let target = &asteroids[index];
+        self.acceleration_at(
+            asteroids,
+            target.pos(),
+            target.radius(),
+            theta,
+            gravity,
+            period,
+        )
+    }
+
+    /// Like `acceleration`, for a body of `radius` at `pos` that needn't be in the tree.
+    /// An asteroid exactly at `pos`, such as the body itself, adds nothing.
+    pub fn acceleration_at(
+        &self,
+        asteroids: &[Asteroid],
+        pos: Vec2,
+        radius: f32,
+        theta: f32,
+        gravity: &Gravity,
+        period: Option<Vec2>,
+    ) -> Vec2 {
+        let mut acc = vec2(0.0, 0.0);
+        if self.nodes.is_empty() {
+            return acc;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.mass <= 0.0 {
+                continue;
+            }
+
+            if node.is_leaf() {
+                for &i in &self.order[node.bodies.clone()] {
+                    let other = &asteroids[i];
+                    let direction = minimum_image(other.pos() - pos, period);
+                    if gravity.applies(direction.length(), radius + other.radius()) {
+                        acc += gravity.acceleration(direction, other.size());
+                    }
+                }
+                continue;
+            }
+
+            let direction = minimum_image(node.center_of_mass - pos, period);
+            let distance = direction.length();
+            let far_enough = node.half_size * 2.0 < theta * distance;
+            if far_enough && !node.spans_images(pos, period) && !node.contains(pos) {
+                acc += gravity.acceleration(direction, node.mass);
+            } else {
+                stack.extend(node.children.iter().filter(|&&c| c != NO_CHILD));
+            }
+        }
+        acc
+    }
+
+    /// Indices of the asteroids whose surface lies within `distance` of `pos`, measured to the
+    /// nearest image with a `period`. `asteroids` must be the same slice the tree was built from.
+    pub fn within(
+        &self,
+        asteroids: &[Asteroid],
+        pos: Vec2,
+        distance: f32,
+        period: Option<Vec2>,
+    ) -> Vec<usize> {
+        let mut found = Vec::new();
+        if self.nodes.is_empty() {
+            return found;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            // Distance from `pos` to the cell along each axis, zero inside it
+            let gap =
+                (minimum_image(node.center - pos, period).abs() - node.half_size).max(Vec2::ZERO);
+            if gap.length() > distance + node.max_radius && !node.spans_images(pos, period) {
+                continue;
+            }
+
+            if node.is_leaf() {
+                found.extend(self.order[node.bodies.clone()].iter().filter(|&&i| {
+                    let other = &asteroids[i];
+                    minimum_image(other.pos() - pos, period).length() - other.radius() <= distance
+                }));
+            } else {
+                stack.extend(node.children.iter().filter(|&&c| c != NO_CHILD));
+            }
+        }
+        found
+    }
+}
+
+/// Moves every element matching `pred` to the front and returns how many there are.
+fn partition(items: &mut [usize], pred: impl Fn(usize) -> bool) -> usize {
+    let mut split = 0;
+    for i in 0..items.len() {
+        if pred(items[i]) {
+            items.swap(split, i);
+            split += 1;
+        }
+    }
+    split
+}
@@ -0,0 +1,260 @@
+use crate::boundary::Boundary;
+use crate::collision::CollisionResponse;
+use crate::fragmentation::Fragmentation;
+use crate::gravity::{Gravity, GravitySolver};
+use crate::integrator::{Integrator, TimeStepping};
+use crate::material::Material;
+use crate::motion::Motion;
+use crate::objects::Asteroid;
+use crate::snapshot::{self, SNAPSHOT_VERSION, Snapshot, SnapshotError, SnapshotFormat};
+use crate::spawn_strategy::{OrbitalDiskStrategy, SpawnStrategy, SpawnView, StrategySnapshot};
+use crate::world::WorldState;
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Bumped whenever a change to `Input` would break reading older recordings.
+pub const RECORDING_VERSION: u32 = 5;
+
+const BINARY_MAGIC: &[u8; 4] = b"ASTR";
+
+/// Something the app does to the world.
+/// The world is deterministic, so replaying the same inputs from the same start reproduces a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Input {
+    /// Arguments of `Ship::apply_control`.
+    Control {
+        rcs_forward: f32,
+        rcs_strafe: f32,
+        rotate: f32,
+        engine_increase: bool,
+        engine_decrease: bool,
+        dt: f32,
+    },
+    CutEngine,
+    /// An asteroid placed by hand.
+    PlaceAsteroid {
+        pos: Vec2,
+        vel: Vec2,
+        size: f32,
+        material: Material,
+    },
+    /// One call of the active spawn strategy.
+    Spawn(SpawnView),
+    SetStrategy(StrategySnapshot),
+    SetGravity(Gravity),
+    SetGravitySolver(GravitySolver),
+    SetIntegrator(Integrator),
+    SetTimeStepping(TimeStepping),
+    SetFragmentation(Option<Fragmentation>),
+    SetBoundary(Boundary),
+    ResetConservationBaseline,
+    /// Simulation speed multiplier. Only changes how fast the app advances the world.
+    SetSpeed(f32),
+    /// Pauses or resumes. Only changes how fast the app advances the world.
+    TogglePause,
+    /// A `WorldState::update` call with this much world time.
+    Advance(f32),
+    SetMotion {
+        id: u64,
+        motion: Option<Motion>,
+    },
+    SetCollisionResponse(CollisionResponse),
+}
+
+impl Input {
+    /// Applies the input to the world. `SetSpeed` and `TogglePause` are left to the caller.
+    pub fn apply(&self, world: &mut WorldState, strategy: &mut Box<dyn SpawnStrategy>) {
+        match self {
+            Input::Control {
+                rcs_forward,
+                rcs_strafe,
+                rotate,
+                engine_increase,
+                engine_decrease,
+                dt,
+            } => world.ship.apply_control(
+                *rcs_forward,
+                *rcs_strafe,
+                *rotate,
+                *engine_increase,
+                *engine_decrease,
+                *dt,
+            ),
+            Input::CutEngine => world.ship.engine_power = 0.0,
+            Input::PlaceAsteroid {
+                pos,
+                vel,
+                size,
+                material,
+            } => {
+                world.spawn(Asteroid::new(*pos, *vel, *size).with_composition(*material));
+            }
+            Input::Spawn(view) => strategy.spawn(world, view),
+            Input::SetStrategy(snapshot) => *strategy = snapshot.clone().into_strategy(),
+            Input::SetGravity(gravity) => world.set_gravity(*gravity),
+            Input::SetGravitySolver(solver) => world.set_gravity_solver(*solver),
+            Input::SetIntegrator(integrator) => world.set_integrator(*integrator),
+            Input::SetTimeStepping(time_stepping) => world.set_time_stepping(*time_stepping),
+            Input::SetFragmentation(fragmentation) => world.set_fragmentation(*fragmentation),
+            Input::SetBoundary(boundary) => world.set_boundary(*boundary),
+            Input::ResetConservationBaseline => world.reset_conservation_baseline(),
+            Input::SetSpeed(_) | Input::TogglePause => {}
+            Input::Advance(duration) => {
+                world.update(*duration);
+            }
+            Input::SetMotion { id, motion } => world.set_motion(*id, motion.clone()),
+            Input::SetCollisionResponse(response) => world.set_collision_response(*response),
+        }
+    }
+}
+
+/// An input together with the world tick it was applied at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedInput {
+    pub tick: u64,
+    pub input: Input,
+}
+
+/// Every input of a session, starting from a snapshot of the world and spawn strategy.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub version: u32,
+    pub start: Snapshot,
+    pub inputs: Vec<RecordedInput>,
+}
+
+impl Recording {
+    /// Starts recording from the current state of `world` and `strategy`.
+    pub fn new(world: &WorldState, strategy: &dyn SpawnStrategy) -> Self {
+        Self {
+            version: RECORDING_VERSION,
+            start: Snapshot::new(world.snapshot(), Some(strategy.snapshot())),
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Appends an input about to be applied to `world`.
+    pub fn record(&mut self, world: &WorldState, input: &Input) {
+        self.inputs.push(RecordedInput {
+            tick: world.tick(),
+            input: input.clone(),
+        });
+    }
+
+    /// World and spawn strategy the recording starts from.
+    pub fn start_state(&self) -> (WorldState, Box<dyn SpawnStrategy>) {
+        let world = WorldState::from_snapshot(self.start.world.clone());
+        let strategy = match &self.start.strategy {
+            Some(strategy) => strategy.clone().into_strategy(),
+            None => Box::new(OrbitalDiskStrategy::new()),
+        };
+        (world, strategy)
+    }
+
+    pub fn to_bytes(&self, format: SnapshotFormat) -> Result<Vec<u8>, SnapshotError> {
+        snapshot::encode(self, format, BINARY_MAGIC)
+    }
+
+    /// Decodes either format. Recordings whose start snapshot was saved with another
+    /// `SNAPSHOT_VERSION` are rejected too, as their inputs may no longer mean the same.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let recording: Self = snapshot::decode(bytes, BINARY_MAGIC, RECORDING_VERSION)?;
+        if recording.start.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion {
+                found: recording.start.version,
+                expected: SNAPSHOT_VERSION,
+            });
+        }
+        Ok(recording)
+    }
+
+    /// Writes the recording in the format picked by `SnapshotFormat::from_path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes(SnapshotFormat::from_path(path))?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+/// The replayed world reached an input at a different tick than it was recorded at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Desync {
+    /// Position of the input in the recording.
+    pub index: usize,
+    pub recorded_tick: u64,
+    pub replayed_tick: u64,
+}
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "replay diverged at input {}: recorded at tick {}, replayed at tick {}",
+            self.index, self.recorded_tick, self.replayed_tick
+        )
+    }
+}
+
+impl std::error::Error for Desync {}
+
+/// Plays a recording back onto a world built with `Recording::start_state`.
+pub struct Replay {
+    inputs: Vec<RecordedInput>,
+    next: usize,
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            inputs: recording.inputs,
+            next: 0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.inputs.len()
+    }
+
+    /// Applies inputs up to and including the next `Advance`, which is one frame of the
+    /// recorded session, and returns them.
+    pub fn play_frame(
+        &mut self,
+        world: &mut WorldState,
+        strategy: &mut Box<dyn SpawnStrategy>,
+    ) -> Result<&[RecordedInput], Desync> {
+        let start = self.next;
+        while let Some(recorded) = self.inputs.get(self.next) {
+            if recorded.tick != world.tick() {
+                return Err(Desync {
+                    index: self.next,
+                    recorded_tick: recorded.tick,
+                    replayed_tick: world.tick(),
+                });
+            }
+
+            recorded.input.apply(world, strategy);
+            self.next += 1;
+            if matches!(recorded.input, Input::Advance(_)) {
+                break;
+            }
+        }
+        Ok(&self.inputs[start..self.next])
+    }
+
+    pub fn play_to_end(
+        &mut self,
+        world: &mut WorldState,
+        strategy: &mut Box<dyn SpawnStrategy>,
+    ) -> Result<(), Desync> {
+        while !self.is_finished() {
+            self.play_frame(world, strategy)?;
+        }
+        Ok(())
+    }
+}
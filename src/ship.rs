@@ -1,5 +1,9 @@
+use crate::boundary::minimum_image;
+use crate::collision::{self, Contact};
+use crate::gravity::Gravity;
 use crate::objects::Asteroid;
 use glam::{Vec2, vec2};
+use serde::{Deserialize, Serialize};
 
 const RCS_ACCELERATION: f32 = 10.0;
 const MAIN_ENGINE_ACCELERATION: f32 = RCS_ACCELERATION * 100.0;
@@ -9,9 +13,22 @@ const SHIP_RADIUS: f32 = 10.0;
 const SHIP_MASS: f32 = 100.0;
 const SHIP_MAX_HEALTH: f32 = 10000.0;
 const COLLISION_DAMAGE_THRESHOLD: f32 = 30.0;
-const RESTITUTION: f32 = 0.5;
-const FRICTION_COEFFICIENT: f32 = 0.5;
+const CONTACT: Contact = Contact {
+    restitution: 0.5,
+    friction: 0.5,
+};
+
+/// One ship–asteroid contact resolved during a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShipContact {
+    pub asteroid: u64,
+    /// Total impulse the asteroid received, the ship got the opposite.
+    pub impulse: Vec2,
+    /// Health the ship lost.
+    pub damage: f32,
+}
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ship {
     pub pos: Vec2,
     pub vel: Vec2,
@@ -51,31 +68,81 @@ impl Ship {
         self.orientation = 0.0;
     }
 
-    pub fn update(&mut self, asteroids: &mut Vec<Asteroid>, dt: f32) {
-        // Calculate gravitational forces from asteroids and collect collisions
+    /// Gravity between the ship, placed at `pos`, and the asteroids.
+    /// Adds the ship's pull on each asteroid to `asteroid_acc` and returns the ship's own acceleration.
+    /// Separations use the nearest periodic image when `period` is set.
+    pub fn gravity_at(
+        &self,
+        pos: Vec2,
+        asteroids: &[Asteroid],
+        asteroid_acc: &mut [Vec2],
+        gravity: &Gravity,
+        period: Option<Vec2>,
+    ) -> Vec2 {
         let mut acc = vec2(0.0, 0.0);
+
+        for (asteroid, asteroid_acc) in asteroids.iter().zip(asteroid_acc.iter_mut()) {
+            let direction = minimum_image(asteroid.pos() - pos, period);
+            let distance = direction.length();
+
+            // Touching bodies are handled by collision response instead
+            if !gravity.applies(distance, self.radius() + asteroid.radius()) {
+                continue;
+            }
+
+            // Apply asteroid's gravity on ship
+            acc += gravity.acceleration(direction, asteroid.size());
+
+            // Apply ship's gravity on asteroid
+            *asteroid_acc += gravity.acceleration(-direction, self.mass());
+        }
+
+        acc
+    }
+
+    /// Contact response between the ship and every asteroid touching it.
+    /// Separations use the nearest periodic image when `period` is set.
+    ///
+    /// `start` holds the positions of every asteroid followed by the ship before the step.
+    /// A ship that passed through an asteroid during the step before touching anything else
+    /// is moved back to where it first touched it, and only that contact is resolved.
+    pub fn resolve_collisions(
+        &mut self,
+        asteroids: &mut [Asteroid],
+        start: &[Vec2],
+        dt: f32,
+        period: Option<Vec2>,
+    ) -> Vec<ShipContact> {
         let mut collision_data = Vec::new();
+        let mut contacts = Vec::new();
 
-        for (i, asteroid) in asteroids.iter_mut().enumerate() {
-            let direction = asteroid.pos() - self.pos;
+        let ship_moved = minimum_image(self.pos - start[asteroids.len()], period);
+        let mut first_overlap = f32::INFINITY;
+        let mut first_pass: Option<(f32, usize, Vec2)> = None;
+        for (i, asteroid) in asteroids.iter().enumerate() {
+            let direction = minimum_image(asteroid.pos() - self.pos, period);
             let distance = direction.length();
+            let radius = self.radius() + asteroid.radius();
+            let moved = minimum_image(asteroid.pos() - start[i], period) - ship_moved;
+            let time = collision::time_of_impact(direction, moved, radius);
 
-            // Check for collision
-            if distance <= (self.radius() + asteroid.radius()) {
+            if distance <= radius {
                 collision_data.push((i, direction, distance));
-            } else {
-                // Apply asteroid's gravity on ship
-                let force_magnitude = asteroid.size() / distance;
-                let force = direction.normalize() * force_magnitude;
-                acc += force;
-
-                // Apply ship's gravity on asteroid
-                let ship_force_magnitude = self.mass() / distance;
-                let ship_force = -direction.normalize() * ship_force_magnitude;
-                asteroid.set_vel(asteroid.vel() + ship_force * dt);
+                first_overlap = first_overlap.min(time.unwrap_or(1.0));
+            } else if let Some(time) = time
+                && first_pass.is_none_or(|(first, _, _)| time < first)
+            {
+                first_pass = Some((time, i, direction - moved * (1.0 - time)));
             }
         }
 
+        if let Some((time, i, direction)) = first_pass
+            && time < first_overlap
+        {
+            self.pos = asteroids[i].pos() - direction;
+            collision_data = vec![(i, direction, direction.length())];
+        }
+
         // Accumulate velocity changes from all collisions
         let mut ship_vel_delta = Vec2::ZERO;
 
@@ -93,56 +160,46 @@ impl Ship {
                 // Calculate relative velocity
                 let relative_vel = asteroid.vel() - self.vel;
                 let relative_vel_magnitude = relative_vel.length();
-                let rel_vel_along_normal = relative_vel.dot(normal);
 
                 // Apply damage if relative velocity is high
+                let mut damage = 0.0;
                 if relative_vel_magnitude > COLLISION_DAMAGE_THRESHOLD {
                     let surplus = relative_vel_magnitude - COLLISION_DAMAGE_THRESHOLD;
-                    self.health -= surplus * asteroid_mass / total_mass;
+                    damage = surplus * asteroid_mass / total_mass;
+                    self.health -= damage;
                 }
 
-                // Separate objects based on their velocities and masses
-                if overlap > 0.0 {
-                    let ship_mass_ratio = ship_mass / total_mass;
-                    let asteroid_mass_ratio = asteroid_mass / total_mass;
-
-                    // Separate in proportion to masses
-                    self.pos -= normal * overlap * asteroid_mass_ratio;
-                    asteroid.set_pos(asteroid.pos() + normal * overlap * ship_mass_ratio);
-                }
-
-                // Impulse magnitude using reduced mass formula
-                let impulse_magnitude =
-                    -(1.0 + RESTITUTION) * rel_vel_along_normal * (ship_mass * asteroid_mass)
-                        / total_mass;
-                let impulse = normal * impulse_magnitude;
-
-                // Apply velocity changes (impulse / mass = velocity change)
+                // Separate in proportion to masses
+                let masses = [ship_mass, asteroid_mass];
+                let [ship_shift, asteroid_shift] = collision::separation(normal, overlap, masses);
+                self.pos += ship_shift;
+                asteroid.set_pos(asteroid.pos() + asteroid_shift);
+
+                // Restitution along the normal and friction along the surface, where the
+                // asteroid's spin drags on the ship and the friction spins the asteroid.
+                // The ship's attitude control soaks up the torque on the ship itself.
+                let contact_point = -normal * asteroid.radius();
+                let surface_vel = asteroid.surface_velocity(contact_point) - self.vel;
+                let rotational_masses = [
+                    f32::INFINITY,
+                    asteroid.moment_of_inertia() / contact_point.length_squared(),
+                ];
+                let impulse =
+                    CONTACT.impulse(normal, distance, surface_vel, masses, rotational_masses, dt);
                 ship_vel_delta -= impulse / ship_mass;
-                asteroid.set_vel(asteroid.vel() + impulse / asteroid_mass);
-
-                // Apply friction for tangential velocity
-                // Calculate tangential component of relative velocity
-                let tangent = Vec2::new(-normal.y, normal.x);
-                let rel_vel_tangent = relative_vel.normalize().dot(tangent);
+                asteroid.apply_impulse(impulse, contact_point);
 
-                // Friction opposes tangential motion, proportional to normal force
-                let normal_force = asteroid_mass * ship_mass / distance;
-                let friction_magnitude = FRICTION_COEFFICIENT * normal_force * rel_vel_tangent;
-                let friction_impulse = -tangent * friction_magnitude;
-
-                // Apply friction impulse
-                acc -= friction_impulse / ship_mass;
-                asteroid.set_vel(asteroid.vel() - friction_impulse / asteroid_mass * dt);
+                contacts.push(ShipContact {
+                    asteroid: asteroid.id(),
+                    impulse,
+                    damage,
+                });
             }
         }
 
         // Apply accumulated velocity changes
         self.vel += ship_vel_delta;
-
-        // Update velocity and position
-        self.vel += acc * dt;
-        self.pos += self.vel * dt;
+        contacts
     }
 
     pub fn draw(&self, fb: &mut crate::framebuffer::FrameBuffer, sprite: &image::RgbaImage) {
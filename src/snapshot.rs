@@ -0,0 +1,198 @@
+use crate::boundary::Boundary;
+use crate::collision::CollisionResponse;
+use crate::diagnostics::{Conservation, Ejected};
+use crate::fragmentation::Fragmentation;
+use crate::gravity::{Gravity, GravitySolver};
+use crate::integrator::{Integrator, TimeStepping};
+use crate::lineage::Lineage;
+use crate::motion::Motion;
+use crate::objects::Asteroid;
+use crate::ship::Ship;
+use crate::spawn_strategy::StrategySnapshot;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Bumped whenever a change to the saved types would break reading older files.
+pub const SNAPSHOT_VERSION: u32 = 9;
+
+// Binary snapshots start with this, JSON ones start with `{`
+const BINARY_MAGIC: &[u8; 4] = b"ASTW";
+
+/// Encoding of a snapshot on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// Pretty-printed JSON, readable and editable by hand.
+    Json,
+    /// Compact bincode payload behind a short magic header.
+    Binary,
+}
+
+impl SnapshotFormat {
+    /// JSON for `.json` files, binary for everything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => SnapshotFormat::Json,
+            _ => SnapshotFormat::Binary,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    UnsupportedVersion { found: u32, expected: u32 },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(error) => write!(f, "I/O error: {}", error),
+            SnapshotError::Json(error) => write!(f, "invalid JSON: {}", error),
+            SnapshotError::Binary(error) => write!(f, "invalid binary data: {}", error),
+            SnapshotError::UnsupportedVersion { found, expected } => write!(
+                f,
+                "file version {} is not supported, expected {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(error: std::io::Error) -> Self {
+        SnapshotError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for SnapshotError {
+    fn from(error: serde_json::Error) -> Self {
+        SnapshotError::Json(error)
+    }
+}
+
+impl From<bincode::Error> for SnapshotError {
+    fn from(error: bincode::Error) -> Self {
+        SnapshotError::Binary(error)
+    }
+}
+
+/// Everything needed to rebuild a `WorldState`.
+/// Runtime statistics such as updates per second aren't saved.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub asteroids: Vec<Asteroid>,
+    pub ship: Ship,
+    pub world_time: f32,
+    pub tick: u64,
+    pub tick_rate: f32,
+    pub gravity: Gravity,
+    pub gravity_solver: GravitySolver,
+    pub integrator: Integrator,
+    pub parallel: bool,
+    pub time_stepping: TimeStepping,
+    pub fragmentation: Option<Fragmentation>,
+    pub collision_response: CollisionResponse,
+    pub boundary: Boundary,
+    pub next_id: u64,
+    pub lineage: Lineage,
+    pub motions: BTreeMap<u64, Motion>,
+    pub ejected: Ejected,
+    pub seed: u64,
+    /// Current state of the world's random generator, so a loaded world continues identically.
+    pub rng_state: u64,
+    pub conservation_baseline: Option<Conservation>,
+}
+
+/// A saved world together with the spawn strategy that was building it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub world: WorldSnapshot,
+    pub strategy: Option<StrategySnapshot>,
+}
+
+impl Snapshot {
+    pub fn new(world: WorldSnapshot, strategy: Option<StrategySnapshot>) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            world,
+            strategy,
+        }
+    }
+
+    pub fn to_bytes(&self, format: SnapshotFormat) -> Result<Vec<u8>, SnapshotError> {
+        encode(self, format, BINARY_MAGIC)
+    }
+
+    /// Decodes either format, telling them apart by the binary header.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        decode(bytes, BINARY_MAGIC, SNAPSHOT_VERSION)
+    }
+
+    /// Writes the snapshot in the format picked by `SnapshotFormat::from_path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes(SnapshotFormat::from_path(path))?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+// Read ahead of the full value so a version mismatch is reported as such
+// rather than as whatever field first fails to parse
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+/// Encodes a versioned value, which must have `version: u32` as its first field.
+pub(crate) fn encode<T: Serialize>(
+    value: &T,
+    format: SnapshotFormat,
+    magic: &[u8; 4],
+) -> Result<Vec<u8>, SnapshotError> {
+    match format {
+        SnapshotFormat::Json => Ok(serde_json::to_vec_pretty(value)?),
+        SnapshotFormat::Binary => {
+            let mut bytes = magic.to_vec();
+            bincode::serialize_into(&mut bytes, value)?;
+            Ok(bytes)
+        }
+    }
+}
+
+/// Decodes a value written by `encode`, in either format.
+pub(crate) fn decode<T: DeserializeOwned>(
+    bytes: &[u8],
+    magic: &[u8; 4],
+    expected: u32,
+) -> Result<T, SnapshotError> {
+    let check_version = |header: Header| {
+        if header.version == expected {
+            Ok(())
+        } else {
+            Err(SnapshotError::UnsupportedVersion {
+                found: header.version,
+                expected,
+            })
+        }
+    };
+
+    if let Some(payload) = bytes.strip_prefix(magic) {
+        check_version(bincode::deserialize(payload)?)?;
+        Ok(bincode::deserialize(payload)?)
+    } else {
+        check_version(serde_json::from_slice(bytes)?)?;
+        Ok(serde_json::from_slice(bytes)?)
+    }
+}
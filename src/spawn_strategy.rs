@@ -1,14 +1,63 @@
-use crate::framebuffer::FrameBuffer;
+use crate::material::{Composition, Material};
 use crate::objects::Asteroid;
 use crate::world::WorldState;
-use glam::{vec2, Vec2};
+use glam::{Vec2, vec2};
+use serde::{Deserialize, Serialize};
+
+/// The part of the camera spawn strategies place new bodies around.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpawnView {
+    pub camera_pos: Vec2,
+    pub camera_vel: Vec2,
+    pub zoom: f32,
+    pub width: f32,
+    pub height: f32,
+    /// World seconds per real second, converts on-screen velocities to world velocities.
+    /// Zero leaves them as they are.
+    pub time_scale: f32,
+}
+
+impl Default for SpawnView {
+    fn default() -> Self {
+        Self {
+            camera_pos: vec2(0.0, 0.0),
+            camera_vel: vec2(0.0, 0.0),
+            zoom: 1.0,
+            width: 1280.0,
+            height: 720.0,
+            time_scale: 0.0,
+        }
+    }
+}
 
 pub trait SpawnStrategy {
-    fn spawn(&mut self, world: &mut WorldState, fb: &FrameBuffer);
+    fn spawn(&mut self, world: &mut WorldState, view: &SpawnView);
 
     fn name(&self) -> &str;
+
+    /// Parameters and progress of the strategy, for saving alongside the world.
+    fn snapshot(&self) -> StrategySnapshot;
+}
+
+/// Saved state of one of the built-in spawn strategies.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StrategySnapshot {
+    Random(RandomScreenSpaceStrategy),
+    Orbital(OrbitalDiskStrategy),
+    SolarSystem(SolarSystemStrategy),
+}
+
+impl StrategySnapshot {
+    pub fn into_strategy(self) -> Box<dyn SpawnStrategy> {
+        match self {
+            StrategySnapshot::Random(strategy) => Box::new(strategy),
+            StrategySnapshot::Orbital(strategy) => Box::new(strategy),
+            StrategySnapshot::SolarSystem(strategy) => Box::new(strategy),
+        }
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RandomScreenSpaceStrategy {
     pub min_size: f32,
     pub size_alpha: f32,
@@ -31,41 +80,56 @@ impl RandomScreenSpaceStrategy {
     }
 }
 
+impl Default for RandomScreenSpaceStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SpawnStrategy for RandomScreenSpaceStrategy {
-    fn spawn(&mut self, world: &mut WorldState, fb: &FrameBuffer) {
-        let width = fb.width() as f32 / fb.zoom;
-        let height = fb.height() as f32 / fb.zoom;
+    fn spawn(&mut self, world: &mut WorldState, view: &SpawnView) {
+        let width = view.width / view.zoom;
+        let height = view.height / view.zoom;
 
-        let x = fb.camera_pos.x + (fastrand::f32() - 0.5) * width;
-        let y = fb.camera_pos.y + (fastrand::f32() - 0.5) * height;
+        let x = view.camera_pos.x + (world.rng().f32() - 0.5) * width;
+        let y = view.camera_pos.y + (world.rng().f32() - 0.5) * height;
         let pos = vec2(x, y);
 
-        let angle = fastrand::f32() * 2.0 * std::f32::consts::PI;
+        let angle = world.rng().f32() * 2.0 * std::f32::consts::PI;
 
-        let speed = power_law_sample(self.min_speed, self.speed_alpha).min(self.max_speed);
+        let speed =
+            power_law_sample(world.rng(), self.min_speed, self.speed_alpha).min(self.max_speed);
         let random_vel = vec2(angle.cos() * speed, angle.sin() * speed);
-        let actual_speed = world.actual_speed();
-        let mut vel = random_vel + fb.camera_vel;
-        if actual_speed > 0.0 {
-            vel /= actual_speed;
+        let mut vel = random_vel + view.camera_vel;
+        if view.time_scale > 0.0 {
+            vel /= view.time_scale;
         }
 
-        let size = power_law_sample(self.min_size, self.size_alpha).min(self.max_size);
+        let size = power_law_sample(world.rng(), self.min_size, self.size_alpha).min(self.max_size);
 
-        world.asteroids.push(Asteroid::new(pos, vel, size));
+        world.spawn_asteroid(pos, vel, size);
     }
 
     fn name(&self) -> &str {
         "Random"
     }
+
+    fn snapshot(&self) -> StrategySnapshot {
+        StrategySnapshot::Random(self.clone())
+    }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct OrbitalDiskStrategy {
     pub min_radius: f32,
     pub max_radius_multiplier: f32,
     pub mean_size: f32,
     pub size_std_dev: f32,
     pub velocity_std_dev: f32,
+    /// Bodies closer than this share of the disk radius are metal.
+    pub metal_line: f32,
+    /// Bodies further out than this share of the disk radius are ice, rock lies in between.
+    pub frost_line: f32,
 }
 
 impl OrbitalDiskStrategy {
@@ -76,38 +140,55 @@ impl OrbitalDiskStrategy {
             mean_size: 5.0,
             size_std_dev: 1.0,
             velocity_std_dev: 0.1,
+            metal_line: 0.2,
+            frost_line: 0.6,
+        }
+    }
+
+    fn material_at(&self, radius: f32, max_radius: f32) -> Material {
+        let share = radius / max_radius;
+        if share < self.metal_line {
+            Material::Metal
+        } else if share > self.frost_line {
+            Material::Ice
+        } else {
+            Material::Rock
         }
     }
 }
 
+impl Default for OrbitalDiskStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SpawnStrategy for OrbitalDiskStrategy {
-    fn spawn(&mut self, world: &mut WorldState, fb: &FrameBuffer) {
+    fn spawn(&mut self, world: &mut WorldState, view: &SpawnView) {
         let center = world.calculate_center_of_mass(true);
 
         // Max radius depends on zoom level (more zoomed out = larger spawn area)
-        let max_radius = self.max_radius_multiplier / fb.zoom;
+        let max_radius = self.max_radius_multiplier / view.zoom;
 
         // Random radius with uniform distribution over circular area
         // Using sqrt to get uniform area distribution (not uniform radius distribution)
-        let u = fastrand::f32();
+        let u = world.rng().f32();
         let radius = self.min_radius + u.sqrt() * (max_radius - self.min_radius);
 
         // Random angle
-        let angle = fastrand::f32() * 2.0 * std::f32::consts::PI;
+        let angle = world.rng().f32() * 2.0 * std::f32::consts::PI;
 
         // Position on disk
         let pos = center + vec2(angle.cos() * radius, angle.sin() * radius);
 
-        // Calculate orbital velocity for linear force decay: F = M / r
-        // For circular orbit: centripetal acceleration = v^2 / r
-        // Force per unit mass: a = M / r
-        // Therefore: v^2 / r = M / r, so v^2 = M
-        // Thus: v = sqrt(M)
+        // Calculate circular orbital velocity under the active force law
+        // For circular orbit: centripetal acceleration = v^2 / r = a(M, r)
+        // For F = M / r this gives v = sqrt(M) at every radius
         let central_mass = world.asteroids.iter().map(|a| a.size()).sum::<f32>();
-        let orbital_speed = central_mass.sqrt();
+        let orbital_speed = world.gravity().circular_speed(central_mass, radius);
 
         // Add velocity perturbation using normal distribution
-        let velocity_perturbation = normal_sample(1.0, self.velocity_std_dev);
+        let velocity_perturbation = normal_sample(world.rng(), 1.0, self.velocity_std_dev);
         let perturbed_speed = orbital_speed * velocity_perturbation;
 
         // Velocity perpendicular to radius (tangential)
@@ -117,36 +198,42 @@ impl SpawnStrategy for OrbitalDiskStrategy {
         );
 
         // Size from normal distribution
-        let size = normal_sample(self.mean_size, self.size_std_dev).max(0.1);
+        let size = normal_sample(world.rng(), self.mean_size, self.size_std_dev).max(0.1);
 
-        world.asteroids.push(Asteroid::new(pos, vel, size));
+        let material = self.material_at(radius, max_radius);
+        world.spawn(Asteroid::new(pos, vel, size).with_composition(material));
     }
 
     fn name(&self) -> &str {
         "Orbital"
     }
+
+    fn snapshot(&self) -> StrategySnapshot {
+        StrategySnapshot::Orbital(self.clone())
+    }
 }
 
-fn power_law_sample(min_value: f32, alpha: f32) -> f32 {
-    let u = fastrand::f32();
+pub fn power_law_sample(rng: &mut fastrand::Rng, min_value: f32, alpha: f32) -> f32 {
+    let u = rng.f32();
     min_value * (1.0 - u).powf(-1.0 / alpha)
 }
 
-fn normal_sample(mean: f32, std_dev: f32) -> f32 {
+fn normal_sample(rng: &mut fastrand::Rng, mean: f32, std_dev: f32) -> f32 {
     // Box-Muller transform to generate normal distribution
-    let u1 = fastrand::f32();
-    let u2 = fastrand::f32();
+    let u1 = rng.f32();
+    let u2 = rng.f32();
     let z0 = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos();
     mean + std_dev * z0
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SolarSystemStrategy {
     star_spawned: bool,
     planets: Vec<PlanetData>,
     total_moons: usize,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct PlanetData {
     pos: Vec2,
     vel: Vec2,
@@ -167,14 +254,23 @@ impl SolarSystemStrategy {
     }
 }
 
+impl Default for SolarSystemStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SpawnStrategy for SolarSystemStrategy {
-    fn spawn(&mut self, world: &mut WorldState, _fb: &FrameBuffer) {
+    fn spawn(&mut self, world: &mut WorldState, _view: &SpawnView) {
         const SHIP_RADIUS: f32 = 10.0;
         const STAR_RADIUS_MULTIPLIER: f32 = 125.0;
         const PLANET_RADIUS_MULTIPLIER: f32 = 25.0;
         const MOON_RADIUS_MULTIPLIER: f32 = 5.0;
         const PLANET_ORBIT_MULTIPLIER: f32 = 10.0;
         const MOON_ORBIT_MULTIPLIER: f32 = 3.0;
+        // Planets have metal cores under rock, moons are ice
+        const PLANET_METAL_FRACTION: f32 = 0.4;
+        const MOON_MATERIAL: Material = Material::Ice;
 
         if self.is_complete() {
             return;
@@ -187,22 +283,19 @@ impl SpawnStrategy for SolarSystemStrategy {
 
             // Spawn star far from ship to avoid immediate collision
             let safe_distance = star_radius * 5.0;
-            let angle = fastrand::f32() * 2.0 * std::f32::consts::PI;
+            let angle = world.rng().f32() * 2.0 * std::f32::consts::PI;
             let star_pos =
                 world.ship.pos + vec2(angle.cos() * safe_distance, angle.sin() * safe_distance);
 
             // Set ship velocity to orbit the star
-            // For F = M / r, orbital velocity is v = sqrt(M)
-            let orbital_speed = star_mass.sqrt();
+            let orbital_speed = world.gravity().circular_speed(star_mass, safe_distance);
 
             // Ship orbits perpendicular to the radius vector (from ship to star)
             // Since angle points from ship to star, perpendicular is (sin, -cos) for counter-clockwise
             world.ship.vel = vec2(angle.sin() * orbital_speed, -angle.cos() * orbital_speed);
 
             self.star_spawned = true;
-            world
-                .asteroids
-                .push(Asteroid::new(star_pos, vec2(0.0, 0.0), star_mass));
+            world.spawn_asteroid(star_pos, vec2(0.0, 0.0), star_mass);
             return;
         }
 
@@ -224,20 +317,19 @@ impl SpawnStrategy for SolarSystemStrategy {
         // Step 2 & 3: Spawn planets or moons
         if self.planets.len() < 10 {
             // Or 50/50 chance if we already have at least one planet
-            let should_spawn_planet = self.planets.is_empty() || fastrand::bool();
+            let should_spawn_planet = self.planets.is_empty() || world.rng().bool();
 
             if should_spawn_planet {
                 let planet_radius = SHIP_RADIUS * PLANET_RADIUS_MULTIPLIER;
                 let planet_mass = planet_radius * planet_radius * std::f32::consts::PI;
 
                 let orbit_radius = star_radius * PLANET_ORBIT_MULTIPLIER;
-                let angle = fastrand::f32() * 2.0 * std::f32::consts::PI;
+                let angle = world.rng().f32() * 2.0 * std::f32::consts::PI;
                 let planet_pos =
                     star_pos + vec2(angle.cos() * orbit_radius, angle.sin() * orbit_radius);
 
-                // Calculate orbital velocity for F = M / r
-                // For circular orbit: v^2 / r = M / r, so v^2 = M, thus v = sqrt(M)
-                let orbital_speed = star_mass.sqrt();
+                // Calculate circular orbital velocity under the active force law
+                let orbital_speed = world.gravity().circular_speed(star_mass, orbit_radius);
                 let planet_vel = vec2(-angle.sin() * orbital_speed, angle.cos() * orbital_speed);
 
                 self.planets.push(PlanetData {
@@ -246,9 +338,14 @@ impl SpawnStrategy for SolarSystemStrategy {
                     radius: planet_radius,
                 });
 
-                world
-                    .asteroids
-                    .push(Asteroid::new(planet_pos, planet_vel, planet_mass));
+                let composition = Composition::mix(&[
+                    (Material::Metal.into(), PLANET_METAL_FRACTION),
+                    (Material::Rock.into(), 1.0 - PLANET_METAL_FRACTION),
+                ]);
+                world.spawn(
+                    Asteroid::new(planet_pos, planet_vel, planet_mass)
+                        .with_composition(composition),
+                );
                 return;
             }
         }
@@ -256,21 +353,20 @@ impl SpawnStrategy for SolarSystemStrategy {
         // Spawn a moon if we have planets and haven't reached 30 moons
         if !self.planets.is_empty() && self.total_moons < 30 {
             // Pick a random planet
-            let planet_idx = fastrand::usize(0..self.planets.len());
+            let planet_idx = world.rng().usize(0..self.planets.len());
             let planet = self.planets[planet_idx].clone();
 
             let moon_radius = SHIP_RADIUS * MOON_RADIUS_MULTIPLIER;
             let moon_mass = moon_radius * moon_radius * std::f32::consts::PI;
 
             let orbit_radius = planet.radius * MOON_ORBIT_MULTIPLIER;
-            let angle = fastrand::f32() * 2.0 * std::f32::consts::PI;
+            let angle = world.rng().f32() * 2.0 * std::f32::consts::PI;
             let moon_relative_pos = vec2(angle.cos() * orbit_radius, angle.sin() * orbit_radius);
             let moon_pos = planet.pos + moon_relative_pos;
 
-            // Calculate orbital velocity around planet for F = M / r
-            // For circular orbit: v^2 / r = M / r, so v^2 = M, thus v = sqrt(M)
+            // Calculate circular orbital velocity around planet under the active force law
             let planet_mass = planet.radius * planet.radius * std::f32::consts::PI;
-            let orbital_speed = planet_mass.sqrt();
+            let orbital_speed = world.gravity().circular_speed(planet_mass, orbit_radius);
             let moon_orbital_vel = vec2(-angle.sin() * orbital_speed, angle.cos() * orbital_speed);
 
             // Add planet's velocity to moon's orbital velocity
@@ -278,13 +374,17 @@ impl SpawnStrategy for SolarSystemStrategy {
 
             self.total_moons += 1;
 
-            world
-                .asteroids
-                .push(Asteroid::new(moon_pos, moon_vel, moon_mass));
+            world.spawn(
+                Asteroid::new(moon_pos, moon_vel, moon_mass).with_composition(MOON_MATERIAL),
+            );
         }
     }
 
     fn name(&self) -> &str {
         "Solar System"
     }
+
+    fn snapshot(&self) -> StrategySnapshot {
+        StrategySnapshot::SolarSystem(self.clone())
+    }
 }
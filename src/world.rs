@@ -1,7 +1,19 @@
+use crate::boundary::Boundary;
+use crate::broad_phase;
+use crate::clusters::{self, Clustering, Group, UnionFind};
+use crate::collision::{self, CollisionResponse, Contact};
+use crate::diagnostics::{Conservation, ConservationDrift, Ejected, Ejection};
+use crate::events::{Event, EventBus, EventKind};
+use crate::fragmentation::Fragmentation;
+use crate::gravity::{Gravity, GravitySolver};
+use crate::integrator::{Integrator, TimeStepping};
+use crate::lineage::{Lineage, Origin};
+use crate::motion::Motion;
 use crate::objects::Asteroid;
 use crate::ship::Ship;
-use glam::{vec2, Vec2};
-use std::collections::HashSet;
+use crate::snapshot::WorldSnapshot;
+use glam::{Vec2, vec2};
+use std::collections::BTreeMap;
 
 const STATS_UPDATE_RATE: f32 = 5.0;
 
@@ -9,69 +21,209 @@ pub struct WorldState {
     pub asteroids: Vec<Asteroid>,
     pub ship: Ship,
     pub world_time: f32,
+    tick: u64,
     tick_rate: f32,
-    cleanup_threshold_multiplier: f32,
+    gravity: Gravity,
+    gravity_solver: GravitySolver,
+    integrator: Integrator,
+    // Accelerations the last step ended with and the positions they were computed at
+    carried_acc: Option<(Vec<Vec2>, Vec<Vec2>)>,
+    parallel: bool,
+    time_stepping: TimeStepping,
+    last_step: f32,
+    fragmentation: Option<Fragmentation>,
+    collision_response: CollisionResponse,
+    boundary: Boundary,
+    next_id: u64,
+    lineage: Lineage,
+    // Pinned and kinematic asteroids by ID, everything else moves freely
+    motions: BTreeMap<u64, Motion>,
+    // Removals since the last drain, the totals cover every one
+    ejections: Vec<Ejection>,
+    ejected: Ejected,
+    events: EventBus,
     update_count: u32,
+    simulated_time: f32,
+    seed: u64,
+    rng: fastrand::Rng,
+    conservation_baseline: Option<Conservation>,
     last_ups_time: std::time::Instant,
     updates_per_second: f32,
+    simulation_speed: f32,
 }
 
 impl Default for WorldState {
     fn default() -> Self {
+        Self::with_seed(fastrand::u64(..))
+    }
+}
+
+impl WorldState {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Creates a world whose randomness is fully determined by `seed`.
+    /// The same seed and the same inputs produce bit-identical worlds.
+    pub fn with_seed(seed: u64) -> Self {
         Self {
             asteroids: Vec::new(),
             ship: Ship::new(vec2(0.0, 0.0)),
             world_time: 0.0,
+            tick: 0,
             tick_rate: 100.0,
-            cleanup_threshold_multiplier: 10.0,
+            gravity: Gravity::default(),
+            gravity_solver: GravitySolver::default(),
+            integrator: Integrator::default(),
+            carried_acc: None,
+            parallel: true,
+            time_stepping: TimeStepping::default(),
+            last_step: 0.0,
+            fragmentation: None,
+            collision_response: CollisionResponse::default(),
+            boundary: Boundary::default(),
+            next_id: 1,
+            lineage: Lineage::default(),
+            motions: BTreeMap::new(),
+            ejections: Vec::new(),
+            ejected: Ejected::default(),
+            events: EventBus::default(),
             update_count: 0,
+            simulated_time: 0.0,
+            seed,
+            rng: fastrand::Rng::with_seed(seed),
+            conservation_baseline: None,
             last_ups_time: std::time::Instant::now(),
             updates_per_second: 0.0,
+            simulation_speed: 0.0,
         }
     }
-}
 
-impl WorldState {
-    pub fn new() -> Self {
-        Default::default()
+    /// Rebuilds a world from a snapshot. It continues exactly as the saved world would have.
+    pub fn from_snapshot(snapshot: WorldSnapshot) -> Self {
+        let mut world = Self::with_seed(snapshot.seed);
+        world.asteroids = snapshot.asteroids;
+        world.ship = snapshot.ship;
+        world.world_time = snapshot.world_time;
+        world.tick = snapshot.tick;
+        world.tick_rate = snapshot.tick_rate;
+        world.gravity = snapshot.gravity;
+        world.gravity_solver = snapshot.gravity_solver;
+        world.integrator = snapshot.integrator;
+        world.parallel = snapshot.parallel;
+        world.time_stepping = snapshot.time_stepping;
+        world.fragmentation = snapshot.fragmentation;
+        world.collision_response = snapshot.collision_response;
+        world.boundary = snapshot.boundary;
+        world.next_id = snapshot.next_id;
+        world.lineage = snapshot.lineage;
+        world.motions = snapshot.motions;
+        world.ejected = snapshot.ejected;
+        world.rng = fastrand::Rng::with_seed(snapshot.rng_state);
+        world.conservation_baseline = snapshot.conservation_baseline;
+        world
     }
 
+    pub fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot {
+            asteroids: self.asteroids.clone(),
+            ship: self.ship.clone(),
+            world_time: self.world_time,
+            tick: self.tick,
+            tick_rate: self.tick_rate,
+            gravity: self.gravity,
+            gravity_solver: self.gravity_solver,
+            integrator: self.integrator,
+            parallel: self.parallel,
+            time_stepping: self.time_stepping,
+            fragmentation: self.fragmentation,
+            collision_response: self.collision_response,
+            boundary: self.boundary,
+            next_id: self.next_id,
+            lineage: self.lineage.clone(),
+            motions: self.motions.clone(),
+            ejected: self.ejected,
+            seed: self.seed,
+            rng_state: self.rng.get_seed(),
+            conservation_baseline: self.conservation_baseline,
+        }
+    }
+
+    /// Advances the world by steps totalling at most `delta_time`.
+    /// Returns the world time actually simulated, the remainder is left for the next call.
+    /// Fixed steps are never split, adaptive steps are cut short to fit the remaining time.
     pub fn update(&mut self, delta_time: f32) -> f32 {
-        let tick_duration = 1.0 / self.tick_rate;
         let mut delta = delta_time;
 
-        while delta > tick_duration {
-            let new_asteroids: Vec<Asteroid> = self
-                .asteroids
-                .iter()
-                .map(|asteroid| {
-                    let mut a = *asteroid;
-                    a.update(&self.asteroids, tick_duration);
-                    a
-                })
-                .collect();
-            self.asteroids = new_asteroids;
-
-            // Update ship
-            self.ship.update(&mut self.asteroids, tick_duration);
+        // Bodies pushed straight into `asteroids` haven't been given an ID yet
+        for asteroid in self.asteroids.iter_mut().filter(|a| a.id() == 0) {
+            asteroid.set_id(self.next_id);
+            self.next_id += 1;
+        }
+
+        if self.conservation_baseline.is_none() && delta > 1.0 / self.tick_rate {
+            self.reset_conservation_baseline();
+        }
+
+        while delta > self.min_step_duration() {
+            let (pos, vel) = self.state();
+            let acc = self.start_accelerations(&pos);
+            let tick_duration = self
+                .step_duration(&pos, &vel, acc.as_deref().unwrap_or_default())
+                .min(delta);
+            // Contacts are swept along the path each body took through the step
+            let start = pos.clone();
+
+            self.integrate(pos, vel, acc, tick_duration);
+            self.last_step = tick_duration;
+            self.confine_bodies();
+
+            // Resolve ship contacts after moving everything, constrained bodies don't give way
+            let contacts = self.ship.resolve_collisions(
+                &mut self.asteroids,
+                &start,
+                tick_duration,
+                self.boundary.period(),
+            );
+            self.apply_motions(self.world_time + tick_duration);
+            for contact in contacts {
+                self.emit(EventKind::ShipCollided {
+                    asteroid: contact.asteroid,
+                    impulse: contact.impulse,
+                    damage: contact.damage,
+                });
+            }
 
             // Check if ship died and respawn
             if self.ship.is_dead() {
+                self.emit(EventKind::ShipDied { pos: self.ship.pos });
                 self.respawn_ship();
+                self.emit(EventKind::ShipRespawned {
+                    pos: self.ship.pos,
+                    vel: self.ship.vel,
+                });
             }
 
-            self.check_collisions();
-            self.cleanup_distant_asteroids();
+            self.check_collisions(&start);
+            match self.boundary {
+                Boundary::Cleanup { threshold } => self.cleanup_distant_asteroids(threshold),
+                // Merges and contacts can leave bodies slightly outside the box
+                _ => self.confine_bodies(),
+            }
 
             self.world_time += tick_duration;
+            self.tick += 1;
             delta -= tick_duration;
 
             self.update_count += 1;
+            self.simulated_time += tick_duration;
             let update_interval = 1.0 / STATS_UPDATE_RATE;
-            if self.last_ups_time.elapsed().as_secs_f32() >= update_interval {
-                self.updates_per_second =
-                    self.update_count as f32 / self.last_ups_time.elapsed().as_secs_f32();
+            let elapsed = self.last_ups_time.elapsed().as_secs_f32();
+            if elapsed >= update_interval {
+                self.updates_per_second = self.update_count as f32 / elapsed;
+                self.simulation_speed = self.simulated_time / elapsed;
                 self.update_count = 0;
+                self.simulated_time = 0.0;
                 self.last_ups_time = std::time::Instant::now();
             }
         }
@@ -79,31 +231,262 @@ impl WorldState {
         delta_time - delta
     }
 
-    fn check_collisions(&mut self) {
-        let mut to_remove = HashSet::new();
-        let mut to_add = Vec::new();
+    /// Positions and velocities of every asteroid followed by the ship.
+    fn state(&self) -> (Vec<Vec2>, Vec<Vec2>) {
+        let mut pos: Vec<Vec2> = self.asteroids.iter().map(|a| a.pos()).collect();
+        let mut vel: Vec<Vec2> = self.asteroids.iter().map(|a| a.vel()).collect();
+        pos.push(self.ship.pos);
+        vel.push(self.ship.vel);
+        (pos, vel)
+    }
 
-        for i in 0..self.asteroids.len() {
-            for j in (i + 1)..self.asteroids.len() {
-                if to_remove.contains(&i) || to_remove.contains(&j) {
-                    continue;
+    fn min_step_duration(&self) -> f32 {
+        match self.time_stepping {
+            TimeStepping::Fixed => 1.0 / self.tick_rate,
+            TimeStepping::Adaptive { min_step, .. } => min_step,
+        }
+    }
+
+    /// Accelerations at `pos` when the integrator or the step length needs them. The ones the
+    /// previous step ended with are reused as long as no body has moved since.
+    fn start_accelerations(&mut self, pos: &[Vec2]) -> Option<Vec<Vec2>> {
+        let carried = self
+            .carried_acc
+            .take()
+            .and_then(|(at, acc)| (at == pos).then_some(acc));
+        let needed = self.integrator.uses_start_acceleration()
+            || matches!(self.time_stepping, TimeStepping::Adaptive { .. });
+        if carried.is_some() || !needed {
+            return carried;
+        }
+        Some(self.accelerations(pos))
+    }
+
+    /// Length of the next step given the current state, see `TimeStepping`.
+    /// `acc` is only read by adaptive stepping.
+    fn step_duration(&self, pos: &[Vec2], vel: &[Vec2], acc: &[Vec2]) -> f32 {
+        let TimeStepping::Adaptive {
+            accuracy,
+            min_step,
+            max_step,
+        } = self.time_stepping
+        else {
+            return 1.0 / self.tick_rate;
+        };
+
+        // Only closing in on another body calls for short steps, so speed is measured
+        // against the nearest one
+        let neighbours =
+            broad_phase::nearest_neighbours(pos, self.boundary.period(), self.parallel);
+        let radii: Vec<f32> = self
+            .asteroids
+            .iter()
+            .map(|a| a.radius())
+            .chain(std::iter::once(self.ship.radius()))
+            .collect();
+
+        let mut step = max_step;
+        for (i, (&radius, acc)) in radii.iter().zip(acc).enumerate() {
+            if let Some(j) = neighbours[i] {
+                // Gap between the surfaces, but at least the body's own radius so touching
+                // bodies about to merge don't stall the world
+                let distance = self.boundary.separation(pos[i], pos[j]).length();
+                let gap = (distance - radius - radii[j]).max(radius);
+                let speed = (vel[i] - vel[j]).length();
+                if speed > 0.0 {
+                    step = step.min(accuracy * gap / speed);
                 }
+            }
+            let acc = acc.length();
+            if acc > 0.0 {
+                step = step.min(accuracy * (radius / acc).sqrt());
+            }
+        }
+
+        step.max(min_step)
+    }
+
+    /// Advances asteroids and the ship together with the active integrator.
+    /// `acc`, when given, must be the accelerations at `pos`.
+    fn integrate(
+        &mut self,
+        mut pos: Vec<Vec2>,
+        mut vel: Vec<Vec2>,
+        acc: Option<Vec<Vec2>>,
+        dt: f32,
+    ) {
+        let end_acc = self
+            .integrator
+            .step(&mut pos, &mut vel, acc, dt, |pos| self.accelerations(pos));
+        self.carried_acc = end_acc.map(|acc| (pos.clone(), acc));
+
+        for (asteroid, (pos, vel)) in self.asteroids.iter_mut().zip(pos.iter().zip(&vel)) {
+            asteroid.set_pos(*pos);
+            asteroid.set_vel(*vel);
+            asteroid.rotate(dt);
+        }
+        self.ship.pos = pos[self.asteroids.len()];
+        self.ship.vel = vel[self.asteroids.len()];
+
+        // Constrained bodies coast through the step and land exactly on their path
+        self.apply_motions(self.world_time + dt);
+    }
+
+    /// Puts every pinned and kinematic asteroid where its motion says it is at `time`.
+    fn apply_motions(&mut self, time: f32) {
+        if self.motions.is_empty() {
+            return;
+        }
+        for asteroid in &mut self.asteroids {
+            if let Some((pos, vel)) = self
+                .motions
+                .get(&asteroid.id())
+                .and_then(|motion| motion.state_at(time))
+            {
+                asteroid.set_pos(pos);
+                asteroid.set_vel(vel);
+                asteroid.set_spin(0.0);
+            }
+        }
+    }
 
-                let a1 = &self.asteroids[i];
-                let a2 = &self.asteroids[j];
+    /// Pins the asteroid or makes it follow a prescribed path, `None` lets it move freely again.
+    /// The body jumps onto its path straight away.
+    pub fn set_motion(&mut self, id: u64, motion: Option<Motion>) {
+        self.carried_acc = None;
+        match motion {
+            Some(motion) => {
+                self.motions.insert(id, motion);
+                self.apply_motions(self.world_time);
+            }
+            None => {
+                self.motions.remove(&id);
+            }
+        }
+    }
+
+    pub fn motion(&self, id: u64) -> Option<&Motion> {
+        self.motions.get(&id)
+    }
 
-                if a1.collides_with(a2) {
-                    let merged = a1.merge_with(a2);
-                    to_add.push(merged);
-                    to_remove.insert(i);
-                    to_remove.insert(j);
+    /// Accelerations of every asteroid followed by the ship, with bodies placed at `pos`.
+    fn accelerations(&self, pos: &[Vec2]) -> Vec<Vec2> {
+        let (asteroid_pos, ship_pos) = pos.split_at(self.asteroids.len());
+        let asteroids: Vec<Asteroid> = self
+            .asteroids
+            .iter()
+            .zip(asteroid_pos)
+            .map(|(asteroid, pos)| {
+                let mut a = *asteroid;
+                a.set_pos(*pos);
+                a
+            })
+            .collect();
+
+        let period = self.boundary.period();
+        let mut acc = self.gravity_solver.accelerations_periodic(
+            &asteroids,
+            &self.gravity,
+            period,
+            self.parallel,
+        );
+        let ship_acc =
+            self.ship
+                .gravity_at(ship_pos[0], &asteroids, &mut acc, &self.gravity, period);
+        if !self.motions.is_empty() {
+            for (asteroid, acc) in self.asteroids.iter().zip(acc.iter_mut()) {
+                if self.motions.contains_key(&asteroid.id()) {
+                    *acc = Vec2::ZERO;
                 }
             }
         }
+        acc.push(ship_acc);
+        acc
+    }
+
+    // `start` holds the positions of every asteroid followed by the ship before the step, so
+    // fast bodies that passed through each other during it still collide
+    fn check_collisions(&mut self, start: &[Vec2]) {
+        let mut moved: Vec<Vec2> = self
+            .asteroids
+            .iter()
+            .zip(start)
+            .map(|(asteroid, &from)| self.boundary.separation(from, asteroid.pos()))
+            .collect();
+        let pairs = match self.boundary {
+            Boundary::Toroidal { center, size } => broad_phase::swept_pairs_periodic(
+                &self.asteroids,
+                &moved,
+                center,
+                size,
+                self.parallel,
+            ),
+            _ => broad_phase::swept_pairs(&self.asteroids, &moved, self.parallel),
+        };
+
+        // Every contact is judged on the state before any of them is resolved
+        let mut clusters = UnionFind::new(self.asteroids.len());
+        let mut bounces = Vec::new();
+        for (i, j) in pairs {
+            let Some(time) = self.contact_time(i, j, &moved) else {
+                continue;
+            };
+            let (a1, a2) = (&self.asteroids[i], &self.asteroids[j]);
+
+            // Constrained bodies absorb what they hit, and coincident centres have no
+            // contact normal to bounce along
+            let constrained =
+                self.motions.contains_key(&a1.id()) || self.motions.contains_key(&a2.id());
+            let relative_speed = (a2.vel() - a1.vel()).length();
+            let offset = self.boundary.separation(a1.pos(), a2.pos());
+            let touching = offset - (moved[j] - moved[i]) * (1.0 - time) != Vec2::ZERO;
+            match self.collision_response.bounce(relative_speed) {
+                Some(contact) if !constrained && touching => bounces.push((i, j, contact)),
+                _ => clusters.union(i, j),
+            }
+        }
+
+        // Chains of overlapping bodies merge in one go, lowest member ID first
+        let mut members: Vec<Vec<usize>> = vec![Vec::new(); self.asteroids.len()];
+        for i in 0..self.asteroids.len() {
+            members[clusters.find(i)].push(i);
+        }
+        let mut merging: Vec<Vec<usize>> = members
+            .into_iter()
+            .filter(|cluster| cluster.len() > 1)
+            .collect();
+        for cluster in &mut merging {
+            cluster.sort_by_key(|&i| self.asteroids[i].id());
+        }
+        merging.sort_by_key(|cluster| self.asteroids[cluster[0]].id());
+
+        let mut to_remove = vec![false; self.asteroids.len()];
+        for &i in merging.iter().flatten() {
+            to_remove[i] = true;
+        }
+
+        // Bodies that merge this step don't bounce, the rest bounce in ID order
+        bounces.retain(|&(i, j, _)| !to_remove[i] && !to_remove[j]);
+        bounces.sort_by_key(|&(i, j, _)| {
+            let (a, b) = (self.asteroids[i].id(), self.asteroids[j].id());
+            (a.min(b), a.max(b))
+        });
+        for (i, j, contact) in bounces {
+            // An earlier bounce may have pushed them apart already
+            if let Some(time) = self.contact_time(i, j, &moved) {
+                self.bounce(i, j, contact, time, &mut moved);
+            }
+        }
+
+        let mut to_add = Vec::new();
+        for cluster in merging {
+            let bodies = self.merge_cluster(&cluster);
+            to_add.extend(bodies);
+        }
 
         let mut idx = 0;
         self.asteroids.retain(|_| {
-            let should_keep = !to_remove.contains(&idx);
+            let should_keep = !to_remove[idx];
             idx += 1;
             should_keep
         });
@@ -111,8 +494,190 @@ impl WorldState {
         self.asteroids.extend(to_add);
     }
 
-    pub fn spawn_asteroid(&mut self, pos: Vec2, vel: Vec2, size: f32) {
-        self.asteroids.push(Asteroid::new(pos, vel, size));
+    // Combines overlapping asteroids, given in ID order, into one body. A violent impact of
+    // just two bodies can shatter them instead when fragmentation is on.
+    fn merge_cluster(&mut self, cluster: &[usize]) -> Vec<Asteroid> {
+        // Positions next to the first body, so clusters straddling a periodic edge stay whole
+        let first = self.asteroids[cluster[0]].pos();
+        let parts: Vec<Asteroid> = cluster
+            .iter()
+            .map(|&i| {
+                let mut body = self.asteroids[i];
+                body.set_pos(self.boundary.nearest_image(first, body.pos()));
+                body
+            })
+            .collect();
+        let parents: Vec<u64> = parts.iter().map(|a| a.id()).collect();
+
+        // A constrained body absorbs the rest and carries on along its path,
+        // the heaviest one wins when several are constrained
+        let carrier = parts
+            .iter()
+            .filter(|a| self.motions.contains_key(&a.id()))
+            .max_by(|a, b| a.size().total_cmp(&b.size()).then(b.id().cmp(&a.id())))
+            .copied();
+        let (bodies, origin) = match (&self.fragmentation, parts.as_slice()) {
+            (Some(model), [a1, a2]) if carrier.is_none() && model.shatters(a1, a2) => {
+                (model.fragment(a1, a2, &mut self.rng), Origin::Fragment)
+            }
+            _ => {
+                let mut merged = Asteroid::merge_all(&parts);
+                if let Some(carrier) = carrier {
+                    merged.set_pos(carrier.pos());
+                    merged.set_vel(carrier.vel());
+                }
+                (vec![merged], Origin::Merge)
+            }
+        };
+        let motion = carrier.and_then(|carrier| self.motions.get(&carrier.id()).cloned());
+        for parent in &parents {
+            self.motions.remove(parent);
+        }
+        // A fragmentation that couldn't split still merged the pair
+        let origin = if bodies.len() == 1 {
+            Origin::Merge
+        } else {
+            origin
+        };
+
+        let mut ids = Vec::with_capacity(bodies.len());
+        let mut added = Vec::with_capacity(bodies.len());
+        for mut body in bodies {
+            let id = self.allocate_id();
+            body.set_id(id);
+            self.lineage.record(id, &parents, self.world_time, origin);
+            ids.push(id);
+            added.push(body);
+        }
+        if let Some(motion) = motion {
+            self.motions.insert(ids[0], motion);
+        }
+        let event = match origin {
+            Origin::Merge => EventKind::Merged {
+                id: added[0].id(),
+                mass: added[0].size(),
+                pos: added[0].pos(),
+                parents,
+            },
+            Origin::Fragment => EventKind::Fragmented {
+                parents: [parents[0], parents[1]],
+                fragments: ids,
+            },
+        };
+        self.emit(event);
+        added
+    }
+
+    // Fraction of the step at which asteroids `i` and `j` first touched, after moving by
+    // `moved` in straight lines. Bodies overlapping at the end always count.
+    fn contact_time(&self, i: usize, j: usize, moved: &[Vec2]) -> Option<f32> {
+        let (a1, a2) = (&self.asteroids[i], &self.asteroids[j]);
+        // Across a periodic edge, collide with the copy of the second body next to the first
+        let offset = self.boundary.separation(a1.pos(), a2.pos());
+        let radius = a1.radius() + a2.radius();
+        let time = collision::time_of_impact(offset, moved[j] - moved[i], radius);
+        if offset.length() <= radius {
+            Some(time.unwrap_or(1.0))
+        } else {
+            time
+        }
+    }
+
+    // Winds two asteroids back to where they touched at `time` through the step, exchanges the
+    // contact impulse there and moves them on for the rest of the step
+    fn bounce(&mut self, i: usize, j: usize, contact: Contact, time: f32, moved: &mut [Vec2]) {
+        let (a1, a2) = (self.asteroids[i], self.asteroids[j]);
+        let rewind = 1.0 - time;
+        let pos1 = a1.pos() - moved[i] * rewind;
+        let image = self.boundary.nearest_image(a1.pos(), a2.pos());
+        let pos2 = image - moved[j] * rewind;
+        let direction = pos2 - pos1;
+        let distance = direction.length();
+        let normal = direction / distance;
+        let masses = [a1.size(), a2.size()];
+
+        let overlap = a1.radius() + a2.radius() - distance;
+        let [shift1, shift2] = collision::separation(normal, overlap, masses);
+        // Friction at the contact point trades spin between the bodies
+        let (contact1, contact2) = (normal * a1.radius(), -normal * a2.radius());
+        let impulse = contact.impulse(
+            normal,
+            distance,
+            a2.surface_velocity(contact2) - a1.surface_velocity(contact1),
+            masses,
+            [
+                a1.moment_of_inertia() / contact1.length_squared(),
+                a2.moment_of_inertia() / contact2.length_squared(),
+            ],
+            self.last_step,
+        );
+        let (mut body1, mut body2) = (a1, a2);
+        body1.apply_impulse(-impulse, contact1);
+        body2.apply_impulse(impulse, contact2);
+
+        let remaining = self.last_step * rewind;
+        let change1 = pos1 + shift1 + body1.vel() * remaining - a1.pos();
+        let change2 = pos2 + shift2 + body2.vel() * remaining - image;
+        body1.set_pos(a1.pos() + change1);
+        body2.set_pos(a2.pos() + change2);
+        self.asteroids[i] = body1;
+        self.asteroids[j] = body2;
+        moved[i] += change1;
+        moved[j] += change2;
+        self.emit(EventKind::Bounced {
+            bodies: [a1.id(), a2.id()],
+            impulse,
+        });
+    }
+
+    /// Adds a rock asteroid and returns its ID.
+    pub fn spawn_asteroid(&mut self, pos: Vec2, vel: Vec2, size: f32) -> u64 {
+        self.spawn(Asteroid::new(pos, vel, size))
+    }
+
+    /// Adds a prepared asteroid, such as one `with_composition`, and returns its new ID.
+    pub fn spawn(&mut self, mut asteroid: Asteroid) -> u64 {
+        let id = self.allocate_id();
+        asteroid.set_id(id);
+        self.asteroids.push(asteroid);
+        self.emit(EventKind::Spawned {
+            id,
+            pos: asteroid.pos(),
+            vel: asteroid.vel(),
+            mass: asteroid.size(),
+        });
+        id
+    }
+
+    /// Where `update` and `spawn_asteroid` report what happened, see `EventKind`.
+    pub fn events(&mut self) -> &mut EventBus {
+        &mut self.events
+    }
+
+    fn emit(&mut self, kind: EventKind) {
+        if self.events.is_listening() {
+            self.events.emit(Event {
+                tick: self.tick,
+                world_time: self.world_time,
+                kind,
+            });
+        }
+    }
+
+    fn allocate_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// The live asteroid with this ID, if it hasn't merged, fragmented or been removed.
+    pub fn asteroid(&self, id: u64) -> Option<&Asteroid> {
+        self.asteroids.iter().find(|a| a.id() == id)
+    }
+
+    /// Which bodies merged or fragmented into which, and when.
+    pub fn lineage(&self) -> &Lineage {
+        &self.lineage
     }
 
     fn respawn_ship(&mut self) {
@@ -124,20 +689,36 @@ impl WorldState {
 
         // Spawn at a safe distance from center
         let spawn_distance = 1000.0;
-        let angle = fastrand::f32() * 2.0 * std::f32::consts::PI;
+        let angle = self.rng.f32() * 2.0 * std::f32::consts::PI;
         let spawn_pos = center + vec2(angle.cos() * spawn_distance, angle.sin() * spawn_distance);
 
-        // Calculate orbital velocity: v = sqrt(M) for F = M / r
-        let orbital_speed = total_mass.sqrt();
+        // Circular orbit around the whole mass as if it were concentrated at the centre
+        let orbital_speed = self.gravity.circular_speed(total_mass, spawn_distance);
         let spawn_vel = vec2(-angle.sin() * orbital_speed, angle.cos() * orbital_speed);
 
         self.ship.respawn(spawn_pos, spawn_vel);
     }
 
+    /// Partitions the asteroids into groups moving together, heaviest first.
+    pub fn groups(&self, clustering: Clustering) -> Vec<Group> {
+        clusters::find_groups(self, clustering)
+    }
+
+    /// Mass-weighted or plain mean position of the asteroids. In a toroidal world every body
+    /// counts at its image nearest the heaviest one, as in `clusters::find_groups`, so bodies
+    /// wrapping across an edge don't make the result jump.
     pub fn calculate_center_of_mass(&self, weighted: bool) -> Vec2 {
-        if self.asteroids.is_empty() {
+        let Some(heaviest) = self
+            .asteroids
+            .iter()
+            .max_by(|a, b| a.size().total_cmp(&b.size()).then(b.id().cmp(&a.id())))
+        else {
             return vec2(0.0, 0.0);
-        }
+        };
+        let reference = match self.boundary.period() {
+            Some(_) => heaviest.pos(),
+            None => Vec2::ZERO,
+        };
 
         let mut total_mass = 0.0;
         let mut weighted_pos = vec2(0.0, 0.0);
@@ -145,10 +726,10 @@ impl WorldState {
         for asteroid in &self.asteroids {
             let mass = if weighted { asteroid.size() } else { 1.0 };
             total_mass += mass;
-            weighted_pos = weighted_pos + asteroid.pos() * mass;
+            weighted_pos += self.boundary.separation(reference, asteroid.pos()) * mass;
         }
 
-        weighted_pos / total_mass
+        self.boundary.wrap(reference + weighted_pos / total_mass)
     }
 
     fn calculate_mass_std(&self, center: Vec2, weighted: bool) -> f32 {
@@ -173,24 +754,205 @@ impl WorldState {
         }
     }
 
-    fn cleanup_distant_asteroids(&mut self) {
+    /// Energy, momentum and angular momentum of the asteroids and the ship, asteroid spin included.
+    /// Potential energy is a brute-force pair sum, so this is O(n²).
+    pub fn conservation(&self) -> Conservation {
+        let mut bodies: Vec<(Vec2, Vec2, f32)> = self
+            .asteroids
+            .iter()
+            .map(|a| (a.pos(), a.vel(), a.size()))
+            .collect();
+        bodies.push((self.ship.pos, self.ship.vel, self.ship.mass()));
+        let measured = Conservation::measure(&bodies, &self.gravity, self.boundary.period());
+
+        // Spin adds to the orbital terms, the ship's attitude isn't counted
+        let (mut spin_energy, mut spin_angular_momentum) = (0.0, 0.0);
+        for asteroid in &self.asteroids {
+            spin_energy += 0.5 * asteroid.spin_angular_momentum() * asteroid.spin();
+            spin_angular_momentum += asteroid.spin_angular_momentum();
+        }
+        Conservation {
+            kinetic_energy: measured.kinetic_energy + spin_energy,
+            angular_momentum: measured.angular_momentum + spin_angular_momentum,
+            ejected: self.ejected,
+            ..measured
+        }
+    }
+
+    /// Values the drift is measured against, captured on the first update unless reset since.
+    pub fn conservation_baseline(&self) -> Option<Conservation> {
+        self.conservation_baseline
+    }
+
+    pub fn reset_conservation_baseline(&mut self) {
+        self.conservation_baseline = Some(self.conservation());
+    }
+
+    pub fn conservation_drift(&self) -> Option<ConservationDrift> {
+        self.conservation_baseline
+            .map(|baseline| self.conservation().drift_from(&baseline))
+    }
+
+    /// Wraps or reflects every body at the box edges, see `Boundary::confine`.
+    fn confine_bodies(&mut self) {
+        if self.boundary.bounds().is_none() {
+            return;
+        }
+
+        for asteroid in &mut self.asteroids {
+            let (mut pos, mut vel) = (asteroid.pos(), asteroid.vel());
+            self.boundary.confine(&mut pos, &mut vel, asteroid.radius());
+            asteroid.set_pos(pos);
+            asteroid.set_vel(vel);
+        }
+        let radius = self.ship.radius();
+        self.boundary
+            .confine(&mut self.ship.pos, &mut self.ship.vel, radius);
+    }
+
+    fn cleanup_distant_asteroids(&mut self, threshold_multiplier: f32) {
         let center = self.calculate_center_of_mass(true);
         let std_dev = self.calculate_mass_std(center, false);
-        let threshold = std_dev * self.cleanup_threshold_multiplier;
+        let threshold = std_dev * threshold_multiplier;
+
+        let (kept, removed) = std::mem::take(&mut self.asteroids)
+            .into_iter()
+            .partition(|asteroid| (asteroid.pos() - center).length() <= threshold);
+        self.asteroids = kept;
+
+        for asteroid in removed {
+            let ejection = Ejection {
+                id: asteroid.id(),
+                mass: asteroid.size(),
+                momentum: asteroid.vel() * asteroid.size(),
+                pos: asteroid.pos(),
+                world_time: self.world_time,
+            };
+            self.ejected.add(&ejection);
+            self.motions.remove(&ejection.id);
+            self.ejections.push(ejection);
+            self.emit(EventKind::Removed(ejection));
+        }
+    }
+
+    /// Totals over every asteroid removed by the cleanup boundary, so conservation drift can
+    /// be told apart from bodies leaving.
+    pub fn ejected(&self) -> Ejected {
+        self.ejected
+    }
+
+    /// Asteroids removed by the cleanup boundary since the last `drain_ejections`, oldest first.
+    /// Each is also reported as `EventKind::Removed`. Snapshots only keep the totals.
+    pub fn ejections(&self) -> &[Ejection] {
+        &self.ejections
+    }
 
-        self.asteroids
-            .retain(|asteroid| (asteroid.pos() - center).length() <= threshold);
+    /// Takes the removals recorded so far, leaving the totals in `ejected` as they are.
+    pub fn drain_ejections(&mut self) -> Vec<Ejection> {
+        std::mem::take(&mut self.ejections)
+    }
+
+    pub fn boundary(&self) -> Boundary {
+        self.boundary
+    }
+
+    pub fn set_boundary(&mut self, boundary: Boundary) {
+        self.carried_acc = None;
+        self.boundary = boundary;
+    }
+
+    pub fn fragmentation(&self) -> Option<Fragmentation> {
+        self.fragmentation
+    }
+
+    /// Enables breaking up high-energy impacts, `None` always merges colliding asteroids.
+    pub fn set_fragmentation(&mut self, fragmentation: Option<Fragmentation>) {
+        self.fragmentation = fragmentation;
+    }
+
+    pub fn collision_response(&self) -> CollisionResponse {
+        self.collision_response
+    }
+
+    /// Chooses whether touching asteroids merge or bounce off each other.
+    pub fn set_collision_response(&mut self, response: CollisionResponse) {
+        self.collision_response = response;
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Random source for everything that affects the simulation, including spawn strategies.
+    pub fn rng(&mut self) -> &mut fastrand::Rng {
+        &mut self.rng
     }
 
     pub fn updates_per_second(&self) -> f32 {
         self.updates_per_second
     }
 
+    /// Number of steps taken since the world was created.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
     pub fn tick_rate(&self) -> f32 {
         self.tick_rate
     }
 
-    pub fn actual_speed(&self) -> f32 {
-        self.updates_per_second() / self.tick_rate()
+    pub fn gravity(&self) -> Gravity {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: Gravity) {
+        self.carried_acc = None;
+        self.gravity = gravity;
+    }
+
+    pub fn gravity_solver(&self) -> GravitySolver {
+        self.gravity_solver
+    }
+
+    pub fn set_gravity_solver(&mut self, solver: GravitySolver) {
+        self.carried_acc = None;
+        self.gravity_solver = solver;
+    }
+
+    pub fn integrator(&self) -> Integrator {
+        self.integrator
+    }
+
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
+    pub fn parallel(&self) -> bool {
+        self.parallel
+    }
+
+    /// Spreads the force pass and the collision broad phase over all cores.
+    /// Results are bit-identical either way, turning it off keeps everything on the calling thread.
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    pub fn time_stepping(&self) -> TimeStepping {
+        self.time_stepping
+    }
+
+    pub fn set_time_stepping(&mut self, time_stepping: TimeStepping) {
+        self.time_stepping = time_stepping;
+    }
+
+    /// Duration of the most recent step.
+    pub fn last_step(&self) -> f32 {
+        self.last_step
+    }
+
+    /// World seconds simulated per real second, measured over the last UPS interval.
+    /// With adaptive stepping this differs from `updates_per_second() / tick_rate()`.
+    pub fn simulated_time_ratio(&self) -> f32 {
+        self.simulation_speed
     }
 }
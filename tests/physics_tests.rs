@@ -1,26 +1,37 @@
-use asteroids::{vector::Vector, world::WorldState};
+use asteroids::boundary::Boundary;
+use asteroids::broad_phase;
+use asteroids::clusters::{Clustering, Group};
+use asteroids::collision::{CollisionResponse, Contact};
+use asteroids::events::EventKind;
+use asteroids::fragmentation::Fragmentation;
+use asteroids::gravity::{ForceLaw, Gravity, GravitySolver};
+use asteroids::integrator::{Integrator, TimeStepping};
+use asteroids::lineage::Origin;
+use asteroids::material::{Composition, Material};
+use asteroids::motion::Motion;
+use asteroids::objects::Asteroid;
+use asteroids::orbit;
+use asteroids::prediction::{PredictionSettings, predict_ship_path};
+use asteroids::replay::{Input, Recording, Replay};
+use asteroids::snapshot::{Snapshot, SnapshotError, SnapshotFormat};
+use asteroids::spawn_strategy::{
+    OrbitalDiskStrategy, SolarSystemStrategy, SpawnStrategy, SpawnView,
+};
+use asteroids::world::WorldState;
+use glam::{Vec2, vec2};
 
 #[test]
 fn test_asteroid_collision_and_momentum() {
     let mut world = WorldState::new();
+    // Park the ship far away so its gravity doesn't disturb the two-body system
+    world.ship.pos = vec2(1.0e12, 0.0);
 
     let large_size = 1000.0;
     let small_size = 100.0;
     let distance_between = 50.0;
 
-    world.spawn_asteroid(
-        Vector { x: 0.0, y: 0.0 },
-        Vector { x: 0.0, y: 0.0 },
-        large_size,
-    );
-    world.spawn_asteroid(
-        Vector {
-            x: distance_between,
-            y: 0.0,
-        },
-        Vector { x: 0.0, y: 0.0 },
-        small_size,
-    );
+    world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), large_size);
+    world.spawn_asteroid(vec2(distance_between, 0.0), vec2(0.0, 0.0), small_size);
 
     let large_initial_pos = world.asteroids[0].pos();
 
@@ -62,3 +73,1444 @@ fn test_asteroid_collision_and_momentum() {
         size_diff
     );
 }
+
+fn random_disk(count: usize, seed: u64) -> Vec<Asteroid> {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    (0..count)
+        .map(|_| {
+            let pos = vec2(rng.f32() - 0.5, rng.f32() - 0.5) * 10000.0;
+            Asteroid::new(pos, vec2(0.0, 0.0), 1.0 + rng.f32() * 10.0)
+        })
+        .collect()
+}
+
+#[test]
+fn test_barnes_hut_matches_brute_force() {
+    let asteroids = random_disk(500, 42);
+    let exact = GravitySolver::BruteForce.accelerations(&asteroids, &Gravity::default(), false);
+
+    let opened = GravitySolver::BarnesHut { theta: 0.0 }.accelerations(
+        &asteroids,
+        &Gravity::default(),
+        false,
+    );
+    for (a, b) in exact.iter().zip(&opened) {
+        assert!(
+            (*a - *b).length() <= 1e-4 * a.length().max(1e-3),
+            "theta = 0 should reproduce the exact sum, expected {}, got {}",
+            a,
+            b
+        );
+    }
+
+    let approximate =
+        GravitySolver::barnes_hut().accelerations(&asteroids, &Gravity::default(), false);
+    let mean_error = exact
+        .iter()
+        .zip(&approximate)
+        .map(|(a, b)| (*a - *b).length() / a.length())
+        .sum::<f32>()
+        / exact.len() as f32;
+    assert!(
+        mean_error < 0.01,
+        "Mean relative error should be small, got {}",
+        mean_error
+    );
+
+    // In a periodic box even a wide opening angle must not pull cells from the wrong image.
+    // Forces there largely cancel, so errors are measured against the typical acceleration
+    let mut rng = fastrand::Rng::with_seed(7);
+    let period = vec2(1000.0, 1000.0);
+    let asteroids: Vec<Asteroid> = (0..500)
+        .map(|_| {
+            let pos = (vec2(rng.f32(), rng.f32()) - 0.5) * period;
+            Asteroid::new(pos, vec2(0.0, 0.0), 1.0 + 9.0 * rng.f32())
+        })
+        .collect();
+    let gravity = Gravity::default();
+    let exact =
+        GravitySolver::BruteForce.accelerations_periodic(&asteroids, &gravity, Some(period), false);
+    let scale = exact.iter().map(|a| a.length()).sum::<f32>() / exact.len() as f32;
+    let mean_error = |theta: f32| {
+        let approximate = GravitySolver::BarnesHut { theta }.accelerations_periodic(
+            &asteroids,
+            &gravity,
+            Some(period),
+            false,
+        );
+        exact
+            .iter()
+            .zip(&approximate)
+            .map(|(a, b)| (*a - *b).length() / scale)
+            .sum::<f32>()
+            / exact.len() as f32
+    };
+    assert!(mean_error(0.0) < 1e-4);
+    assert!(mean_error(1.5) < 0.25, "Periodic error {}", mean_error(1.5));
+}
+
+#[test]
+fn test_broad_phase_finds_every_colliding_pair() {
+    // Dense cluster so plenty of bodies overlap
+    let asteroids: Vec<Asteroid> = random_disk(2000, 7)
+        .into_iter()
+        .map(|a| Asteroid::new(a.pos() * 0.05, a.vel(), a.size() * 50.0))
+        .collect();
+
+    let candidates = broad_phase::candidate_pairs(&asteroids, false);
+    assert!(candidates.windows(2).all(|w| w[0] < w[1]));
+
+    let mut colliding = 0;
+    for i in 0..asteroids.len() {
+        for j in (i + 1)..asteroids.len() {
+            if asteroids[i].collides_with(&asteroids[j]) {
+                colliding += 1;
+                assert!(
+                    candidates.binary_search(&(i, j)).is_ok(),
+                    "Colliding pair ({}, {}) missing from broad phase",
+                    i,
+                    j
+                );
+            }
+        }
+    }
+    assert!(colliding > 0, "Test setup should produce collisions");
+}
+
+#[test]
+fn test_integrators_on_harmonic_oscillator() {
+    // a = -x has the exact solution x = cos(t)
+    let dt = 0.01;
+    let steps = 400;
+    let exact = vec2((dt * steps as f32).cos(), 0.0);
+
+    // Error after `steps` steps and the number of force evaluations it took
+    let error = |integrator: Integrator| {
+        let mut pos = [vec2(1.0, 0.0)];
+        let mut vel = [vec2(0.0, 0.0)];
+        let evaluations = std::cell::Cell::new(0);
+        let mut acc = None;
+        for _ in 0..steps {
+            acc = integrator.step(&mut pos, &mut vel, acc, dt, |pos| {
+                evaluations.set(evaluations.get() + 1);
+                pos.iter().map(|p| -*p).collect()
+            });
+        }
+        ((pos[0] - exact).length(), evaluations.get())
+    };
+
+    let (euler, euler_evaluations) = error(Integrator::SemiImplicitEuler);
+    let (leapfrog, leapfrog_evaluations) = error(Integrator::Leapfrog);
+    let (verlet, verlet_evaluations) = error(Integrator::VelocityVerlet);
+    let (rk4, rk4_evaluations) = error(Integrator::Rk4);
+
+    // Both second order schemes need a single force evaluation per step
+    assert_eq!(euler_evaluations, steps);
+    assert_eq!(leapfrog_evaluations, steps);
+    assert_eq!(verlet_evaluations, steps + 1);
+    assert_eq!(rk4_evaluations, 4 * steps);
+
+    assert!(euler < 1e-2, "Euler error too large: {}", euler);
+    assert!(leapfrog < 1e-4, "Leapfrog error too large: {}", leapfrog);
+    assert!(verlet < 1e-4, "Verlet error too large: {}", verlet);
+    assert!(rk4 < 1e-4, "RK4 error too large: {}", rk4);
+    assert!(leapfrog < euler && rk4 < euler);
+}
+
+#[test]
+fn test_same_seed_gives_identical_worlds() {
+    let run = |seed: u64| {
+        let mut world = WorldState::with_seed(seed);
+        for asteroid in random_disk(50, 3) {
+            world.spawn_asteroid(asteroid.pos(), asteroid.vel(), asteroid.size());
+        }
+        world.ship.health = 0.0;
+        world.update(1.0);
+        let ship = (world.ship.pos, world.ship.vel);
+        let asteroids: Vec<_> = world
+            .asteroids
+            .iter()
+            .map(|a| (a.pos(), a.vel(), a.size()))
+            .collect();
+        (ship, asteroids)
+    };
+
+    assert_eq!(run(1234), run(1234));
+    assert_ne!(
+        run(1234).0,
+        run(4321).0,
+        "Ship respawn should depend on the seed"
+    );
+}
+
+#[test]
+fn test_fragmentation_conserves_mass_and_momentum() {
+    let model = Fragmentation::default();
+    let mut a = Asteroid::new(vec2(0.0, 0.0), vec2(150.0, 0.0), 400.0);
+    let b = Asteroid::new(vec2(5.0, 2.0), vec2(-150.0, 30.0), 250.0);
+    a.set_spin(0.3);
+    assert!(model.shatters(&a, &b));
+
+    let mut rng = fastrand::Rng::with_seed(11);
+    let fragments = model.fragment(&a, &b, &mut rng);
+    assert!(fragments.len() > 1, "High-speed impact should fragment");
+
+    let mass: f32 = fragments.iter().map(|f| f.size()).sum();
+    let momentum: Vec2 = fragments.iter().map(|f| f.vel() * f.size()).sum();
+    let center: Vec2 = fragments.iter().map(|f| f.pos() * f.size()).sum::<Vec2>() / mass;
+
+    assert!((mass - 650.0).abs() < 1e-3, "Mass changed: {}", mass);
+    let expected_momentum = a.vel() * a.size() + b.vel() * b.size();
+    assert!(
+        (momentum - expected_momentum).length() < 1e-2 * expected_momentum.length(),
+        "Momentum changed: expected {}, got {}",
+        expected_momentum,
+        momentum
+    );
+    let expected_center = (a.pos() * a.size() + b.pos() * b.size()) / 650.0;
+    assert!((center - expected_center).length() < 1e-3);
+
+    // Spin and the off-centre impact both end up in the fragments, as orbits or spin
+    let angular_momentum = |bodies: &[Asteroid]| -> f32 {
+        let center_vel = expected_momentum / 650.0;
+        bodies
+            .iter()
+            .map(|f| {
+                f.spin_angular_momentum()
+                    + f.size() * (f.pos() - expected_center).perp_dot(f.vel() - center_vel)
+            })
+            .sum()
+    };
+    let expected_angular_momentum = angular_momentum(&[a, b]);
+    assert!(
+        (angular_momentum(&fragments) - expected_angular_momentum).abs()
+            < 1e-2 * expected_angular_momentum.abs()
+    );
+    assert!(fragments.iter().all(|f| f.spin() != 0.0));
+
+    for (i, f1) in fragments.iter().enumerate() {
+        for f2 in &fragments[i + 1..] {
+            assert!(!f1.collides_with(f2), "Fragments should not overlap");
+        }
+    }
+
+    let slow = Asteroid::new(b.pos(), a.vel() + vec2(1.0, 0.0), 250.0);
+    assert!(!model.shatters(&a, &slow), "Gentle impacts should merge");
+}
+
+#[test]
+fn test_circular_orbit_follows_force_law() {
+    for law in [ForceLaw::Logarithmic, ForceLaw::Newtonian] {
+        let gravity = Gravity {
+            law,
+            constant: 2.0,
+            softening: 0.0,
+        };
+        let mut world = WorldState::with_seed(0);
+        world.set_gravity(gravity);
+        world.set_integrator(Integrator::Leapfrog);
+        world.ship.pos = vec2(1.0e12, 0.0);
+
+        let star_mass = 1.0e6;
+        let radius = 2000.0;
+        let speed = gravity.circular_speed(star_mass, radius);
+        world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), star_mass);
+        world.spawn_asteroid(vec2(radius, 0.0), vec2(0.0, speed), 1e-3);
+
+        // Roughly a quarter of an orbit
+        let quarter_orbit = std::f32::consts::PI * radius / (2.0 * speed);
+        world.update(quarter_orbit);
+
+        let distance = (world.asteroids[1].pos() - world.asteroids[0].pos()).length();
+        assert!(
+            (distance - radius).abs() < 0.01 * radius,
+            "{} orbit should stay circular, radius {} became {}",
+            law.name(),
+            radius,
+            distance
+        );
+    }
+}
+
+#[test]
+fn test_softening_regularises_overlapping_bodies() {
+    let gravity = Gravity {
+        law: ForceLaw::Newtonian,
+        constant: 1.0,
+        softening: 10.0,
+    };
+    let a = Asteroid::new(vec2(0.0, 0.0), vec2(0.0, 0.0), 1000.0);
+    let b = Asteroid::new(vec2(1.0, 0.0), vec2(0.0, 0.0), 1000.0);
+
+    assert_eq!(
+        a.acceleration_towards(&b, &Gravity::default()),
+        Vec2::ZERO,
+        "Without softening overlapping bodies skip the force"
+    );
+    let softened = a.acceleration_towards(&b, &gravity);
+    assert!(softened.x > 0.0 && softened.x < 1000.0 / 100.0);
+}
+
+#[test]
+fn test_conservation_diagnostics_track_an_orbit() {
+    let mut world = WorldState::with_seed(0);
+    world.set_integrator(Integrator::Leapfrog);
+    world.ship.pos = vec2(1.0e6, 0.0);
+    world.ship.vel = vec2(0.0, world.gravity().circular_speed(1.0e5, 1.0e6));
+
+    let radius = 500.0;
+    let speed = world.gravity().circular_speed(1.0e5, radius);
+    world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 1.0e5);
+    world.spawn_asteroid(vec2(radius, 0.0), vec2(0.0, speed), 10.0);
+
+    let initial = world.conservation();
+    assert!(initial.kinetic_energy > 0.0);
+    assert!(initial.angular_momentum > 0.0, "Counter-clockwise orbit");
+
+    world.update(5.0);
+
+    let baseline = world
+        .conservation_baseline()
+        .expect("Baseline set on first update");
+    assert_eq!(baseline, initial);
+
+    let drift = world.conservation_drift().unwrap();
+    assert!(
+        drift.energy.abs() < 1e-3,
+        "Energy drifted by {}",
+        drift.energy
+    );
+    assert!(
+        drift.angular_momentum.abs() < 1e-3,
+        "Angular momentum drifted by {}",
+        drift.angular_momentum
+    );
+    assert!(drift.momentum.length() < 1e-2 * speed * 10.0);
+}
+
+#[test]
+fn test_adaptive_step_shrinks_for_close_encounters() {
+    let mut world = WorldState::with_seed(0);
+    world.set_time_stepping(TimeStepping::Adaptive {
+        accuracy: 0.2,
+        min_step: 1e-5,
+        max_step: 0.05,
+    });
+    world.ship.pos = vec2(1.0e12, 0.0);
+    world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 1.0e3);
+    world.spawn_asteroid(vec2(1.0e5, 0.0), vec2(0.0, 0.0), 1.0e3);
+
+    let simulated = world.update(1.0);
+    assert!((world.world_time - simulated).abs() < 1e-6);
+    assert!(simulated <= 1.0 && simulated > 0.9);
+    let calm_step = world.last_step();
+
+    // A small body racing past the first one
+    world.spawn_asteroid(vec2(20.0, 0.0), vec2(0.0, 300.0), 1.0);
+    world.update(0.01);
+    assert!(
+        world.last_step() < calm_step / 10.0,
+        "Step should shrink near a fast, close body: {} vs {}",
+        world.last_step(),
+        calm_step
+    );
+}
+
+#[test]
+fn test_adaptive_step_keeps_pace_in_a_calm_disk() {
+    // A thousand bodies on circular orbits around a star, none about to meet another
+    let mut world = WorldState::with_seed(3);
+    world.set_boundary(Boundary::Open);
+    world.set_gravity_solver(GravitySolver::barnes_hut());
+    world.ship.pos = vec2(1.0e12, 0.0);
+    let star_mass = 5000.0;
+    world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), star_mass);
+    let mut rng = fastrand::Rng::with_seed(3);
+    for _ in 0..1000 {
+        let radius = 100.0 + 400.0 * rng.f32();
+        let angle = rng.f32() * 2.0 * std::f32::consts::PI;
+        let direction = vec2(angle.cos(), angle.sin());
+        let speed = world.gravity().circular_speed(star_mass, radius);
+        world.spawn_asteroid(direction * radius, direction.perp() * speed, 5.0);
+    }
+    let fixed_step = 1.0 / world.tick_rate();
+
+    // Every update of one fixed step length should take a single step
+    world.set_time_stepping(TimeStepping::adaptive());
+    for _ in 0..20 {
+        world.update(fixed_step);
+        assert!(
+            (world.last_step() - fixed_step).abs() < 1e-6,
+            "{}",
+            world.last_step()
+        );
+    }
+}
+
+#[test]
+fn test_parallel_update_matches_single_threaded() {
+    let run = |parallel: bool| {
+        let mut world = WorldState::with_seed(5);
+        world.set_parallel(parallel);
+        world.set_gravity_solver(GravitySolver::barnes_hut());
+        for asteroid in random_disk(300, 9) {
+            world.spawn_asteroid(asteroid.pos() * 0.1, asteroid.vel(), asteroid.size() * 20.0);
+        }
+        world.update(0.5);
+        world
+            .asteroids
+            .iter()
+            .map(|a| (a.pos(), a.vel(), a.size()))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(run(true), run(false));
+}
+
+#[test]
+fn test_strategies_spawn_without_a_window() {
+    let spawn = |seed: u64| {
+        let mut world = WorldState::with_seed(seed);
+        let mut strategy = SolarSystemStrategy::new();
+        for _ in 0..100 {
+            strategy.spawn(&mut world, &SpawnView::default());
+        }
+        world
+            .asteroids
+            .iter()
+            .map(|a| (a.pos(), a.vel(), a.size()))
+            .collect::<Vec<_>>()
+    };
+
+    let system = spawn(99);
+    assert_eq!(system.len(), 41, "Star, 10 planets and 30 moons");
+    assert_eq!(system, spawn(99));
+}
+
+#[test]
+fn test_snapshot_round_trip_continues_identically() {
+    let mut world = WorldState::with_seed(5);
+    world.set_fragmentation(Some(Fragmentation::default()));
+    world.set_integrator(Integrator::VelocityVerlet);
+    let mut strategy = SolarSystemStrategy::new();
+    for _ in 0..20 {
+        strategy.spawn(&mut world, &SpawnView::default());
+    }
+    world.update(0.5);
+
+    let snapshot = Snapshot::new(world.snapshot(), Some(strategy.snapshot()));
+    let restored: Vec<_> = [SnapshotFormat::Json, SnapshotFormat::Binary]
+        .into_iter()
+        .map(|format| {
+            let bytes = snapshot.to_bytes(format).unwrap();
+            let loaded = Snapshot::from_bytes(&bytes).unwrap();
+            let world = WorldState::from_snapshot(loaded.world);
+            assert_eq!(world.world_time, snapshot.world.world_time);
+            assert_eq!(world.tick_rate(), snapshot.world.tick_rate);
+            (format, world, loaded.strategy.unwrap().into_strategy())
+        })
+        .collect();
+
+    // The live world carries on without a round trip, so anything the snapshot misses shows up
+    let advance = |world: &mut WorldState, strategy: &mut dyn SpawnStrategy| {
+        for _ in 0..30 {
+            strategy.spawn(world, &SpawnView::default());
+        }
+        world.update(0.5);
+    };
+    advance(&mut world, &mut strategy);
+    let state = |w: &WorldState| serde_json::to_string(&w.snapshot()).unwrap();
+    for (format, mut restored, mut restored_strategy) in restored {
+        advance(&mut restored, restored_strategy.as_mut());
+        assert_eq!(state(&restored), state(&world), "{:?}", format);
+    }
+
+    let mut future = snapshot.clone();
+    future.version += 1;
+    let bytes = future.to_bytes(SnapshotFormat::Binary).unwrap();
+    assert!(matches!(
+        Snapshot::from_bytes(&bytes),
+        Err(SnapshotError::UnsupportedVersion { .. })
+    ));
+}
+
+#[test]
+fn test_replay_reproduces_a_recorded_session() {
+    let mut world = WorldState::with_seed(11);
+    world.set_time_stepping(TimeStepping::adaptive());
+    let mut strategy: Box<dyn SpawnStrategy> = Box::new(OrbitalDiskStrategy::new());
+    let mut recording = Recording::new(&world, strategy.as_ref());
+
+    let mut play = |input: Input, world: &mut WorldState, strategy: &mut Box<dyn SpawnStrategy>| {
+        recording.record(world, &input);
+        input.apply(world, strategy);
+    };
+    for frame in 0..60 {
+        play(
+            Input::Spawn(SpawnView::default()),
+            &mut world,
+            &mut strategy,
+        );
+        play(
+            Input::Control {
+                rcs_forward: 1.0,
+                rcs_strafe: 0.0,
+                rotate: 0.5,
+                engine_increase: frame % 2 == 0,
+                engine_decrease: false,
+                dt: 1.0 / 60.0,
+            },
+            &mut world,
+            &mut strategy,
+        );
+        if frame == 30 {
+            let gravity = Gravity {
+                law: ForceLaw::Newtonian,
+                ..Gravity::default()
+            };
+            play(Input::SetGravity(gravity), &mut world, &mut strategy);
+        }
+        // Uneven frame times, like a real session
+        let duration = 0.01 + 0.005 * (frame % 3) as f32;
+        play(Input::Advance(duration), &mut world, &mut strategy);
+    }
+
+    let bytes = recording.to_bytes(SnapshotFormat::Binary).unwrap();
+    let loaded = Recording::from_bytes(&bytes).unwrap();
+    let (mut replayed, mut replayed_strategy) = loaded.start_state();
+    let mut replay = Replay::new(loaded.clone());
+    replay
+        .play_to_end(&mut replayed, &mut replayed_strategy)
+        .unwrap();
+
+    assert_eq!(replayed.tick(), world.tick());
+    assert_eq!(replayed.ship.pos, world.ship.pos);
+    let state = |w: &WorldState| {
+        w.asteroids
+            .iter()
+            .map(|a| (a.pos(), a.vel(), a.size()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(state(&replayed), state(&world));
+
+    // Recordings store inputs by variant index, so new variants go at the end of `Input`
+    let variant = |input: &Input| bincode::serialize(input).unwrap()[..4].to_vec();
+    assert_eq!(variant(&Input::TogglePause), 13u32.to_le_bytes());
+    assert_eq!(
+        variant(&Input::SetCollisionResponse(CollisionResponse::default())),
+        16u32.to_le_bytes()
+    );
+
+    // A recording whose start snapshot is from another snapshot version is refused
+    let mut outdated = loaded.clone();
+    outdated.start.version -= 1;
+    let bytes = outdated.to_bytes(SnapshotFormat::Binary).unwrap();
+    assert!(matches!(
+        Recording::from_bytes(&bytes),
+        Err(SnapshotError::UnsupportedVersion { .. })
+    ));
+
+    // A recording that doesn't match the world it's played on is reported
+    let mut tampered = loaded;
+    tampered.inputs.last_mut().unwrap().tick += 1;
+    let (mut replayed, mut replayed_strategy) = tampered.start_state();
+    let mut replay = Replay::new(tampered);
+    assert!(
+        replay
+            .play_to_end(&mut replayed, &mut replayed_strategy)
+            .is_err()
+    );
+}
+
+#[test]
+fn test_lineage_tracks_merges_back_to_seed_bodies() {
+    let mut world = WorldState::with_seed(3);
+    world.ship.pos = vec2(1.0e12, 0.0);
+    let a = world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 100.0);
+    let b = world.spawn_asteroid(vec2(1.0, 0.0), vec2(0.0, 0.0), 50.0);
+    // c only reaches the body a and b merge into
+    let c = world.spawn_asteroid(vec2(-5.0, 0.0), vec2(0.0, 0.0), 25.0);
+    let far = world.spawn_asteroid(vec2(1.0e4, 0.0), vec2(0.0, 0.0), 1.0);
+    assert_eq!([a, b, c, far], [1, 2, 3, 4]);
+
+    // a and b merge on the first tick, c joins the result on the next one
+    world.update(0.05);
+    let planet = world
+        .asteroids
+        .iter()
+        .find(|asteroid| asteroid.id() != far)
+        .unwrap();
+    assert_eq!(world.asteroids.len(), 2);
+    assert_eq!(planet.size(), 175.0);
+
+    let lineage = world.lineage();
+    assert_eq!(lineage.len(), 2);
+    assert_eq!(lineage.seed_bodies(planet.id()), vec![a, b, c]);
+    assert_eq!(lineage.seed_bodies(far), vec![far]);
+
+    let first_merge = lineage.children(a)[0];
+    assert_eq!(lineage.parents(first_merge), &[a, b]);
+    assert_eq!(lineage.descendants(a), vec![first_merge, planet.id()]);
+    assert_eq!(lineage.ancestors(planet.id()), vec![a, b, c, first_merge]);
+
+    let formed = lineage.entry(planet.id()).unwrap();
+    assert_eq!(formed.origin, Origin::Merge);
+    assert!(formed.world_time > lineage.entry(first_merge).unwrap().world_time);
+    assert!(world.asteroid(a).is_none());
+    assert!(world.asteroid(planet.id()).is_some());
+}
+
+#[test]
+fn test_toroidal_boundary_wraps_and_merges_across_edges() {
+    let boundary = Boundary::Toroidal {
+        center: vec2(0.0, 0.0),
+        size: vec2(100.0, 100.0),
+    };
+    let mut world = WorldState::with_seed(5);
+    world.set_boundary(boundary);
+    world.ship.pos = vec2(25.0, 0.0);
+
+    // Gravity between bodies near opposite edges acts across the edge, not through the box
+    let left = Asteroid::new(vec2(-48.0, 0.0), vec2(0.0, 0.0), 1.0);
+    let right = Asteroid::new(vec2(48.0, 0.0), vec2(0.0, 0.0), 1.0);
+    let gravity = world.gravity();
+    let acc = GravitySolver::BruteForce.accelerations_periodic(
+        &[left, right],
+        &gravity,
+        boundary.period(),
+        false,
+    );
+    assert!(acc[0].x < 0.0 && acc[1].x > 0.0);
+
+    // The centre of mass of a pair straddling the edge sits on the edge, not mid-box
+    world.asteroids = vec![left, Asteroid::new(vec2(46.0, 0.0), vec2(0.0, 0.0), 1.0)];
+    let center = world.calculate_center_of_mass(true);
+    assert!((center.x.abs() - 49.0).abs() < 1e-3, "{:?}", center);
+    world.asteroids.clear();
+
+    // A body leaving through one edge comes back through the other
+    world.spawn_asteroid(vec2(0.0, -49.0), vec2(0.0, -100.0), 1.0);
+    world.update(0.05);
+    let pos = world.asteroids[0].pos();
+    assert!(pos.y > 40.0 && pos.y < 50.0, "not wrapped: {:?}", pos);
+
+    // Bodies overlapping across the edge merge
+    world.asteroids.clear();
+    world.spawn_asteroid(vec2(-49.9, -20.0), vec2(0.0, 0.0), 50.0);
+    world.spawn_asteroid(vec2(49.9, -20.0), vec2(0.0, 0.0), 50.0);
+    world.update(0.05);
+    assert_eq!(world.asteroids.len(), 1);
+    assert_eq!(world.asteroids[0].size(), 100.0);
+    let merged = world.asteroids[0].pos();
+    assert!(
+        merged.x.abs() > 49.0 && merged.x.abs() <= 50.0,
+        "{:?}",
+        merged
+    );
+}
+
+#[test]
+fn test_reflective_boundary_keeps_bodies_inside() {
+    let mut world = WorldState::with_seed(6);
+    world.set_boundary(Boundary::Reflective {
+        center: vec2(0.0, 0.0),
+        size: vec2(100.0, 100.0),
+    });
+    world.ship.pos = vec2(0.0, 0.0);
+    world.spawn_asteroid(vec2(45.0, 20.0), vec2(200.0, 0.0), 1.0);
+    world.update(0.1);
+
+    let asteroid = &world.asteroids[0];
+    assert!(asteroid.pos().x < 50.0);
+    assert!(asteroid.vel().x < 0.0);
+}
+
+#[test]
+fn test_cleanup_records_ejected_mass_and_momentum() {
+    let mut world = WorldState::with_seed(7);
+    world.set_boundary(Boundary::Cleanup { threshold: 1.5 });
+    world.ship.pos = vec2(0.0, -30.0);
+    for pos in [vec2(100.0, 0.0), vec2(-100.0, 0.0), vec2(0.0, 100.0)] {
+        world.spawn_asteroid(pos, vec2(0.0, 0.0), 10.0);
+    }
+    let stray = world.spawn_asteroid(vec2(1.0e4, 0.0), vec2(5.0, 0.0), 2.0);
+
+    world.events().set_queued(true);
+    world.update(0.05);
+    world.reset_conservation_baseline();
+    assert_eq!(world.asteroids.len(), 3);
+
+    // The world records each removal, and reports it as an event too
+    let ejections = world.ejections().to_vec();
+    assert_eq!(ejections.len(), 1);
+    assert_eq!(ejections[0].id, stray);
+    assert_eq!(ejections[0].mass, 2.0);
+    assert!(ejections[0].pos.x > 1.0e4);
+    let removed: Vec<_> = world
+        .events()
+        .drain()
+        .into_iter()
+        .filter_map(|event| match event.kind {
+            EventKind::Removed(ejection) => Some(ejection),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(removed, ejections);
+    assert_eq!(world.ejected().count, 1);
+    assert_eq!(world.ejected().mass, 2.0);
+    assert_eq!(world.ejected().momentum, ejections[0].momentum);
+    assert!((world.ejected().momentum.x - 10.0).abs() < 0.1);
+
+    // The baseline already includes the ejection, so no further ejected mass is reported
+    let drift = world.conservation_drift().unwrap();
+    assert_eq!(drift.ejected_mass, 0.0);
+    assert_eq!(world.conservation().ejected, world.ejected());
+
+    // Only the totals are saved
+    let restored = WorldState::from_snapshot(world.snapshot());
+    assert_eq!(restored.ejected(), world.ejected());
+    assert!(restored.ejections().is_empty());
+
+    // Draining empties the record but keeps the totals
+    assert_eq!(world.drain_ejections(), ejections);
+    assert!(world.ejections().is_empty());
+    assert_eq!(world.ejected().count, 1);
+}
+
+#[test]
+fn test_event_bus_reports_spawns_merges_and_ship_contacts() {
+    let mut world = WorldState::with_seed(8);
+    world.set_boundary(Boundary::Open);
+    world.ship.pos = vec2(0.0, 0.0);
+
+    let (sender, subscribed) = std::sync::mpsc::channel();
+    world
+        .events()
+        .subscribe(move |event| sender.send(event.clone()).unwrap());
+    // Subscribers only have to be `Send`, state they keep to themselves needn't be `Sync`
+    let seen = std::cell::Cell::new(0);
+    world.events().subscribe(move |_| seen.set(seen.get() + 1));
+    world.events().set_queued(true);
+
+    let rock = world.spawn_asteroid(vec2(14.0, 0.0), vec2(-100.0, 0.0), 100.0);
+    let a = world.spawn_asteroid(vec2(500.0, 0.0), vec2(0.0, 0.0), 10.0);
+    let b = world.spawn_asteroid(vec2(501.0, 0.0), vec2(0.0, 0.0), 10.0);
+    world.update(0.05);
+
+    let events = world.events().drain();
+    assert_eq!(events, subscribed.try_iter().collect::<Vec<_>>());
+    assert!(world.events().drain().is_empty());
+
+    let spawned: Vec<u64> = events
+        .iter()
+        .filter_map(|event| match event.kind {
+            EventKind::Spawned { id, .. } => Some(id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(spawned, vec![rock, a, b]);
+
+    let merged = events.iter().find_map(|event| match &event.kind {
+        EventKind::Merged {
+            id, parents, mass, ..
+        } => Some((*id, parents.clone(), *mass)),
+        _ => None,
+    });
+    let (id, parents, mass) = merged.expect("no merge event");
+    assert_eq!(parents, vec![a, b]);
+    assert_eq!(mass, 20.0);
+    assert!(world.asteroid(id).is_some());
+
+    let hit = events.iter().find_map(|event| match event.kind {
+        EventKind::ShipCollided {
+            asteroid,
+            impulse,
+            damage,
+        } => Some((asteroid, impulse, damage)),
+        _ => None,
+    });
+    let (asteroid, impulse, damage) = hit.expect("no ship contact event");
+    assert_eq!(asteroid, rock);
+    assert!(impulse.x > 0.0);
+    assert!(damage > 0.0);
+    assert!(events.iter().all(|event| event.tick <= world.tick()));
+}
+
+#[test]
+fn test_ship_prediction_follows_orbit_and_flags_collisions() {
+    let mut world = WorldState::with_seed(9);
+    world.set_boundary(Boundary::Open);
+    let star = world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 10000.0);
+    let radius = 300.0;
+    let speed = world.gravity().circular_speed(10000.0, radius);
+    world.ship.pos = vec2(radius, 0.0);
+    world.ship.vel = vec2(0.0, speed);
+
+    let settings = PredictionSettings {
+        horizon: 10.0,
+        step: 0.01,
+        ..Default::default()
+    };
+    let before = (world.ship.pos, world.ship.vel);
+    let orbit = predict_ship_path(&world, &settings);
+    assert_eq!((world.ship.pos, world.ship.vel), before);
+    assert_eq!(orbit.path.len(), 1001);
+    assert!(orbit.collision.is_none());
+    for pos in &orbit.path {
+        assert!((pos.length() - radius).abs() < 0.05 * radius, "{:?}", pos);
+    }
+
+    // Falling straight in ends the path at the surface of the star
+    world.ship.vel = vec2(-50.0, 0.0);
+    let fall = predict_ship_path(&world, &settings);
+    let collision = fall.collision.expect("no collision predicted");
+    assert_eq!(collision.asteroid, star);
+    assert_eq!(fall.path.last(), Some(&collision.ship_pos));
+    assert!(collision.ship_pos.x < radius && collision.ship_pos.x > 0.0);
+    assert!(collision.time < settings.horizon);
+
+    // A small body just off the line of flight shows up as a close pass
+    world.asteroids.clear();
+    world.ship.pos = vec2(-500.0, 0.0);
+    world.ship.vel = vec2(100.0, 0.0);
+    let rock = world.spawn_asteroid(vec2(0.0, 40.0), vec2(0.0, 0.0), 1.0);
+    let pass = predict_ship_path(&world, &settings);
+    assert!(pass.collision.is_none());
+    assert_eq!(pass.approaches.len(), 1);
+    assert_eq!(pass.approaches[0].asteroid, rock);
+    assert!((pass.approaches[0].time - 5.0).abs() < 0.5);
+
+    // Long horizons stretch the step instead of taking ever more of them
+    let long = predict_ship_path(
+        &world,
+        &PredictionSettings {
+            horizon: 600.0,
+            ..settings
+        },
+    );
+    assert_eq!(long.path.len(), settings.max_steps + 1);
+
+    // The body pulling hardest on the ship moves, even when a heavier one is further away
+    world.asteroids.clear();
+    world.ship.pos = vec2(0.0, 0.0);
+    world.ship.vel = vec2(0.0, 0.0);
+    world.spawn_asteroid(vec2(1.0e5, 0.0), vec2(0.0, 0.0), 1000.0);
+    let incoming = world.spawn_asteroid(vec2(200.0, 0.0), vec2(-100.0, 0.0), 10.0);
+    let hit = predict_ship_path(
+        &world,
+        &PredictionSettings {
+            moving_bodies: 1,
+            ..settings
+        },
+    );
+    let collision = hit.collision.expect("the nearby body should move");
+    assert_eq!(collision.asteroid, incoming);
+    assert!(collision.time < 2.0);
+}
+
+#[test]
+fn test_ship_prediction_cost_stays_flat_in_a_crowded_field() {
+    // A ring of 20000 bodies around a quiet lane, with the old per-step sum over every
+    // body this took tens of seconds in a debug build
+    let mut world = WorldState::with_seed(11);
+    world.set_boundary(Boundary::Open);
+    let mut rng = fastrand::Rng::with_seed(11);
+    for _ in 0..20000 {
+        let angle = rng.f32() * std::f32::consts::TAU;
+        let distance = 3000.0 + rng.f32() * 20000.0;
+        world.spawn_asteroid(Vec2::from_angle(angle) * distance, Vec2::ZERO, 5.0);
+    }
+    world.ship.pos = vec2(-500.0, 0.0);
+    world.ship.vel = vec2(100.0, 0.0);
+    let rock = world.spawn_asteroid(vec2(0.0, 40.0), vec2(0.0, 0.0), 1.0);
+
+    let settings = PredictionSettings {
+        horizon: 10.0,
+        step: 0.01,
+        ..Default::default()
+    };
+    let started = std::time::Instant::now();
+    let prediction = predict_ship_path(&world, &settings);
+    let elapsed = started.elapsed();
+
+    assert_eq!(prediction.path.len(), settings.max_steps + 1);
+    assert!(prediction.collision.is_none());
+    assert_eq!(prediction.approaches.len(), 1);
+    assert_eq!(prediction.approaches[0].asteroid, rock);
+    assert!(elapsed.as_secs_f32() < 2.0, "prediction took {elapsed:?}");
+}
+
+#[test]
+fn test_orbital_elements_relative_to_dominant_body() {
+    let mut world = WorldState::with_seed(10);
+    world.set_boundary(Boundary::Open);
+    world.set_gravity(Gravity {
+        law: ForceLaw::Newtonian,
+        constant: 1.0,
+        softening: 0.0,
+    });
+    let gravity = world.gravity();
+
+    // Star, planet and moon, each on a circular orbit around the one before
+    let star = world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 10000.0);
+    let planet_speed = gravity.circular_speed(10100.0, 1000.0);
+    let planet = world.spawn_asteroid(vec2(1000.0, 0.0), vec2(0.0, planet_speed), 100.0);
+    let moon_speed = gravity.circular_speed(101.0, 30.0);
+    let moon = world.spawn_asteroid(vec2(1030.0, 0.0), vec2(0.0, planet_speed + moon_speed), 1.0);
+
+    let moon_orbit = orbit::asteroid_orbit(&world, moon).unwrap();
+    assert_eq!(moon_orbit.primary, planet);
+    assert!(moon_orbit.eccentricity < 1e-3);
+    assert!((moon_orbit.semi_major_axis - 30.0).abs() < 0.1);
+    assert_eq!(orbit::asteroid_orbit(&world, planet).unwrap().primary, star);
+    assert!(orbit::asteroid_orbit(&world, star).is_none());
+
+    // The ship starts at periapsis of an eccentric orbit around the star, 90° round
+    world.ship.pos = vec2(0.0, 400.0);
+    let mu = 10100.0;
+    world.ship.vel = vec2(-1.2 * (mu / 400.0f32).sqrt(), 0.0);
+    let elements = orbit::ship_orbit(&world).unwrap();
+    assert_eq!(elements.primary, star);
+    assert!((elements.eccentricity - 0.44).abs() < 1e-3);
+    assert!((elements.periapsis - 400.0).abs() < 0.5);
+    let a = 400.0 / (1.0 - 0.44);
+    assert!((elements.semi_major_axis - a).abs() < 1.0);
+    assert!((elements.apoapsis.unwrap() - a * 1.44).abs() < 1.0);
+    let period = 2.0 * std::f32::consts::PI * (a * a * a / mu).sqrt();
+    assert!((elements.period.unwrap() / period - 1.0).abs() < 1e-3);
+    assert!((elements.argument_of_periapsis - std::f32::consts::FRAC_PI_2).abs() < 1e-3);
+
+    // The numeric path for other laws agrees with Kepler, also halfway round the orbit
+    let primary = *world.asteroid(star).unwrap();
+    let offset = vec2(-300.0, 250.0);
+    let vel = vec2(-3.0, -4.0);
+    let exact = orbit::orbital_elements(&gravity, &primary, offset, vel, 1.0).unwrap();
+    let softened = Gravity {
+        softening: 1e-3,
+        ..gravity
+    };
+    let numeric = orbit::orbital_elements(&softened, &primary, offset, vel, 1.0).unwrap();
+    assert!((numeric.eccentricity - exact.eccentricity).abs() < 1e-3);
+    assert!((numeric.periapsis / exact.periapsis - 1.0).abs() < 1e-3);
+    assert!((numeric.apoapsis.unwrap() / exact.apoapsis.unwrap() - 1.0).abs() < 1e-3);
+    assert!((numeric.period.unwrap() / exact.period.unwrap() - 1.0).abs() < 1e-2);
+    assert!((numeric.argument_of_periapsis - exact.argument_of_periapsis).abs() < 1e-2);
+
+    // Under 1/r circular orbits are still circular, with period 2πr / v
+    let logarithmic = Gravity {
+        law: ForceLaw::Logarithmic,
+        ..gravity
+    };
+    let speed = logarithmic.circular_speed(10001.0, 500.0);
+    let circular = orbit::orbital_elements(
+        &logarithmic,
+        &primary,
+        vec2(500.0, 0.0),
+        vec2(0.0, speed),
+        1.0,
+    )
+    .unwrap();
+    assert!(circular.eccentricity < 1e-3);
+    assert!((circular.semi_major_axis - 500.0).abs() < 1.0);
+    let period = 2.0 * std::f32::consts::PI * 500.0 / speed;
+    assert!((circular.period.unwrap() / period - 1.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_groups_split_planets_with_their_moons() {
+    let mut world = WorldState::with_seed(11);
+    world.set_boundary(Boundary::Open);
+    let star = world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 10000.0);
+    let planet = world.spawn_asteroid(vec2(1000.0, 0.0), vec2(0.0, 3.0), 200.0);
+    let moons = [
+        world.spawn_asteroid(vec2(1040.0, 0.0), vec2(0.0, 5.0), 1.0),
+        world.spawn_asteroid(vec2(960.0, 0.0), vec2(0.0, 1.0), 1.0),
+    ];
+    let other = world.spawn_asteroid(vec2(-1500.0, 0.0), vec2(0.0, -2.0), 50.0);
+
+    let groups = world.groups(Clustering::HillSpheres);
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[0].members, vec![star]);
+    assert_eq!(groups[1].members, vec![planet, moons[0], moons[1]]);
+    assert_eq!(groups[1].primary, planet);
+    assert_eq!(groups[1].mass, 202.0);
+    assert!((groups[1].barycenter - vec2(1000.0, 0.0)).length() < 1e-3);
+    assert!((groups[1].velocity - vec2(0.0, 3.0)).length() < 1e-3);
+    assert_eq!(groups[2].members, vec![other]);
+
+    // Bodies pushed straight into the world all have ID 0, and are still told apart
+    let mut unnumbered = WorldState::with_seed(11);
+    unnumbered.set_boundary(Boundary::Open);
+    unnumbered.asteroids = world.asteroids.clone();
+    for asteroid in &mut unnumbered.asteroids {
+        *asteroid = Asteroid::new(asteroid.pos(), asteroid.vel(), asteroid.size());
+    }
+    let masses = |groups: &[Group]| groups.iter().map(|g| g.mass).collect::<Vec<_>>();
+    assert_eq!(
+        masses(&unnumbered.groups(Clustering::HillSpheres)),
+        masses(&groups)
+    );
+
+    // Friends-of-friends chains the planet and moons, but not the distant bodies
+    let linked = world.groups(Clustering::FriendsOfFriends {
+        linking_length: 50.0,
+    });
+    assert_eq!(linked.len(), 3);
+    assert!(
+        linked
+            .iter()
+            .any(|group| group.members.len() == 3 && group.contains(planet))
+    );
+    let everything = world.groups(Clustering::FriendsOfFriends {
+        linking_length: 5000.0,
+    });
+    assert_eq!(everything.len(), 1);
+    assert_eq!(everything[0].mass, 10252.0);
+
+    // A group follows its members without searching again, until they are all gone
+    let system = linked.iter().find(|group| group.contains(planet)).unwrap();
+    let body = world
+        .asteroids
+        .iter_mut()
+        .find(|a| a.id() == planet)
+        .unwrap();
+    body.set_pos(body.pos() + vec2(10.0, 0.0));
+    let refreshed = system.refreshed(&world).unwrap();
+    assert_eq!(refreshed.members, system.members);
+    assert!(refreshed.barycenter.x > system.barycenter.x);
+    world.asteroids.retain(|a| !system.contains(a.id()));
+    assert!(system.refreshed(&world).is_none());
+
+    // Friends-of-friends links across a toroidal edge
+    let mut world = WorldState::with_seed(4);
+    world.set_boundary(Boundary::Toroidal {
+        center: vec2(0.0, 0.0),
+        size: vec2(1000.0, 1000.0),
+    });
+    let left = world.spawn_asteroid(vec2(-495.0, 0.0), vec2(0.0, 0.0), 1.0);
+    let right = world.spawn_asteroid(vec2(495.0, 0.0), vec2(0.0, 0.0), 1.0);
+    let groups = world.groups(Clustering::FriendsOfFriends {
+        linking_length: 20.0,
+    });
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].members, vec![left, right]);
+    assert!(groups[0].barycenter.x.abs() > 499.0);
+}
+
+#[test]
+fn test_pinned_and_kinematic_bodies() {
+    let mut world = WorldState::with_seed(5);
+    world.ship.pos = vec2(1.0e12, 0.0);
+    world.set_boundary(Boundary::Open);
+    let star = world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 1000.0);
+    let rock = world.spawn_asteroid(vec2(100.0, 0.0), vec2(0.0, 0.0), 500.0);
+    world.set_motion(
+        star,
+        Some(Motion::Pinned {
+            pos: vec2(0.0, 0.0),
+        }),
+    );
+
+    // The pinned star pulls the rock in without moving, then swallows it where it stands
+    for _ in 0..600 {
+        world.update(0.1);
+        if world.asteroids.len() == 1 {
+            break;
+        }
+        assert_eq!(world.asteroid(star).unwrap().pos(), vec2(0.0, 0.0));
+        assert!(world.asteroid(rock).unwrap().pos().x < 100.0);
+    }
+    assert_eq!(world.asteroids.len(), 1);
+    let merged = world.asteroids[0];
+    assert_eq!(merged.size(), 1500.0);
+    assert_eq!(merged.pos(), vec2(0.0, 0.0));
+    assert_eq!(merged.vel(), vec2(0.0, 0.0));
+    assert!(matches!(
+        world.motion(merged.id()),
+        Some(Motion::Pinned { .. })
+    ));
+    assert!(world.motion(star).is_none());
+
+    // Ship contacts don't spin it either
+    world.ship.pos = vec2(0.0, merged.radius() + world.ship.radius() - 0.5);
+    world.ship.vel = vec2(50.0, 0.0);
+    world.update(1.5 / world.tick_rate());
+    assert!(world.ship.vel.x < 50.0);
+    assert_eq!(world.asteroid(merged.id()).unwrap().spin(), 0.0);
+    assert_eq!(world.asteroid(merged.id()).unwrap().pos(), vec2(0.0, 0.0));
+    world.ship.pos = vec2(1.0e12, 0.0);
+    world.ship.vel = vec2(0.0, 0.0);
+
+    // Kinematic bodies follow their motion whatever gravity says
+    let moon = world.spawn_asteroid(vec2(300.0, 0.0), vec2(0.0, 0.0), 1.0);
+    let start = world.world_time;
+    world.set_motion(
+        moon,
+        Some(Motion::orbit_through(
+            vec2(0.0, 0.0),
+            vec2(300.0, 0.0),
+            0.1,
+            start,
+        )),
+    );
+    let probe = world.spawn_asteroid(vec2(-1000.0, 0.0), vec2(0.0, 0.0), 1.0);
+    world.set_motion(
+        probe,
+        Some(Motion::Path {
+            keyframes: vec![
+                (start, vec2(-1000.0, 0.0)),
+                (start + 10.0, vec2(-1000.0, 200.0)),
+            ],
+            looped: false,
+        }),
+    );
+    world.update(5.0);
+
+    let elapsed = world.world_time - start;
+    let moon = *world.asteroid(moon).unwrap();
+    let angle = 0.1 * elapsed;
+    assert!((moon.pos() - vec2(angle.cos(), angle.sin()) * 300.0).length() < 1e-2);
+    assert!((moon.vel() - vec2(-angle.sin(), angle.cos()) * 30.0).length() < 1e-3);
+    let probe = *world.asteroid(probe).unwrap();
+    assert!((probe.pos() - vec2(-1000.0, 20.0 * elapsed)).length() < 1e-2);
+    assert_eq!(probe.vel(), vec2(0.0, 20.0));
+    assert_eq!(world.asteroid(merged.id()).unwrap().pos(), vec2(0.0, 0.0));
+
+    // Released bodies fall freely again
+    world.set_motion(probe.id(), None);
+    world.update(1.0);
+    assert!(world.asteroid(probe.id()).unwrap().vel().x > 0.0);
+}
+
+#[test]
+fn test_bounce_and_merge_below_collision_responses() {
+    let head_on = |response: CollisionResponse, speed: f32| {
+        let mut world = WorldState::with_seed(2);
+        world.ship.pos = vec2(1.0e12, 0.0);
+        world.set_boundary(Boundary::Open);
+        world.set_gravity(Gravity {
+            constant: 0.0,
+            ..Gravity::default()
+        });
+        world.set_collision_response(response);
+        world.events().set_queued(true);
+        world.spawn_asteroid(vec2(-50.0, 0.0), vec2(speed, 0.0), 100.0);
+        world.spawn_asteroid(vec2(50.0, 0.0), vec2(-speed, 0.0), 300.0);
+        world.update(200.0 / speed);
+        world
+    };
+
+    // Perfectly elastic head-on bounce: both bodies survive and momentum and energy are kept
+    let elastic = Contact {
+        restitution: 1.0,
+        friction: 0.0,
+    };
+    let mut world = head_on(CollisionResponse::Bounce(elastic), 10.0);
+    assert_eq!(world.asteroids.len(), 2);
+    let (light, heavy) = (world.asteroids[0], world.asteroids[1]);
+    let momentum = light.vel() * light.size() + heavy.vel() * heavy.size();
+    assert!((momentum - vec2(-2000.0, 0.0)).length() < 1e-2);
+    let energy = 0.5 * light.size() * light.vel().length_squared()
+        + 0.5 * heavy.size() * heavy.vel().length_squared();
+    assert!((energy - 20000.0).abs() < 1.0);
+    // 1D elastic result for masses 100 and 300 at +10 and -10
+    assert!((light.vel() - vec2(-20.0, 0.0)).length() < 1e-2);
+    assert!((heavy.vel() - vec2(0.0, 0.0)).length() < 1e-2);
+    assert!(light.pos().x < heavy.pos().x - light.radius() - heavy.radius());
+    let bounces = world
+        .events()
+        .drain()
+        .into_iter()
+        .filter(|event| matches!(event.kind, EventKind::Bounced { .. }))
+        .count();
+    assert_eq!(bounces, 1);
+
+    // Inelastic bounces lose energy but keep momentum
+    let world = head_on(CollisionResponse::Bounce(Contact::default()), 10.0);
+    let (light, heavy) = (world.asteroids[0], world.asteroids[1]);
+    let momentum = light.vel() * light.size() + heavy.vel() * heavy.size();
+    assert!((momentum - vec2(-2000.0, 0.0)).length() < 1e-2);
+    assert!((heavy.vel().x - light.vel().x - 10.0).abs() < 1e-2);
+
+    // Merging only below a relative speed: gentle contacts stick, fast ones bounce
+    let rubble = CollisionResponse::MergeBelow {
+        speed: 30.0,
+        contact: elastic,
+    };
+    let world = head_on(rubble, 10.0);
+    assert_eq!(world.asteroids.len(), 1);
+    assert_eq!(world.asteroids[0].size(), 400.0);
+    let world = head_on(rubble, 20.0);
+    assert_eq!(world.asteroids.len(), 2);
+}
+
+#[test]
+fn test_overlapping_bodies_merge_in_one_pass() {
+    let bodies = [
+        (vec2(0.0, 0.0), vec2(1.0, 0.0), 100.0),
+        (vec2(3.0, 0.0), vec2(0.0, 2.0), 50.0),
+        (vec2(6.0, 0.0), vec2(-3.0, 0.0), 40.0),
+        (vec2(8.5, 1.0), vec2(0.0, -1.0), 10.0),
+        (vec2(500.0, 0.0), vec2(0.0, 0.0), 1.0),
+    ];
+    let run = |order: &[usize]| {
+        let mut world = WorldState::with_seed(4);
+        world.ship.pos = vec2(1.0e12, 0.0);
+        world.set_boundary(Boundary::Open);
+        world.events().set_queued(true);
+        for &i in order {
+            let (pos, vel, size) = bodies[i];
+            world.spawn_asteroid(pos, vel, size);
+        }
+        world.update(0.05);
+        world
+    };
+
+    // A chain of four touching bodies becomes one in a single step
+    let mut world = run(&[0, 1, 2, 3, 4]);
+    assert_eq!(world.asteroids.len(), 2);
+    assert_eq!(world.lineage().len(), 1);
+    let merged = *world.asteroids.iter().find(|a| a.size() > 1.0).unwrap();
+    assert_eq!(merged.size(), 200.0);
+    let momentum: Vec2 = world.asteroids.iter().map(|a| a.vel() * a.size()).sum();
+    assert!((momentum - vec2(100.0 - 120.0, 100.0 - 10.0)).length() < 1e-2);
+    assert_eq!(world.lineage().parents(merged.id()), &[1, 2, 3, 4]);
+    let parents = world
+        .events()
+        .drain()
+        .into_iter()
+        .find_map(|event| match event.kind {
+            EventKind::Merged { parents, .. } => Some(parents),
+            _ => None,
+        });
+    assert_eq!(parents, Some(vec![1, 2, 3, 4]));
+
+    // Spawning the same bodies in another order gives the same merged body
+    let shuffled = run(&[3, 4, 1, 0, 2]);
+    let other = shuffled.asteroids.iter().find(|a| a.size() > 1.0).unwrap();
+    assert_eq!(other.size(), merged.size());
+    assert!((other.pos() - merged.pos()).length() < 1e-4);
+    assert!((other.vel() - merged.vel()).length() < 1e-4);
+}
+
+#[test]
+fn test_fast_bodies_collide_instead_of_tunnelling() {
+    let setup = |response: CollisionResponse| {
+        let mut world = WorldState::with_seed(6);
+        world.ship.pos = vec2(1.0e12, 0.0);
+        world.set_boundary(Boundary::Open);
+        world.set_time_stepping(TimeStepping::Fixed);
+        world.set_gravity(Gravity {
+            constant: 0.0,
+            ..Gravity::default()
+        });
+        world.set_collision_response(response);
+        world.events().set_queued(true);
+        world
+    };
+    let step = |world: &WorldState| 1.0 / world.tick_rate();
+    // `update` only takes a step while more than one is left
+    let advance = |world: &mut WorldState| world.update(1.5 * step(world));
+
+    // The rock starts and ends the step well clear of the planet, but crosses it in between
+    let mut world = setup(CollisionResponse::Merge);
+    let speed = 400.0 / step(&world);
+    let planet = world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 10000.0);
+    world.spawn_asteroid(vec2(-200.0, 0.0), vec2(speed, 0.0), 1.0);
+    advance(&mut world);
+    assert_eq!(world.asteroids.len(), 1);
+    assert_eq!(world.asteroids[0].size(), 10001.0);
+    assert_eq!(
+        world.lineage().parents(world.asteroids[0].id()),
+        &[planet, 2]
+    );
+
+    // Bouncing off the near side sends it back the way it came
+    let elastic = Contact {
+        restitution: 1.0,
+        friction: 0.0,
+    };
+    let mut world = setup(CollisionResponse::Bounce(elastic));
+    world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 10000.0);
+    let rock = world.spawn_asteroid(vec2(-200.0, 0.0), vec2(speed, 0.0), 1.0);
+    advance(&mut world);
+    let rock = *world.asteroid(rock).unwrap();
+    assert!(rock.vel().x < -0.99 * speed);
+    // It ends up where its path mirrored at the surface takes it
+    let surface = -world.asteroids[0].radius() - rock.radius();
+    assert!((rock.pos().x - (2.0 * surface - 200.0)).abs() < 1.0);
+
+    // The ship stops at the surface of a small planet it would have flown through
+    let mut world = setup(CollisionResponse::Merge);
+    let planet = world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 1000.0);
+    world.ship.pos = vec2(-40.0, 0.0);
+    world.ship.vel = vec2(80.0 / step(&world), 0.0);
+    advance(&mut world);
+    assert!(world.ship.pos.x < 0.0);
+    assert!(world.ship.vel.x < 0.0);
+    assert!(!world.ship.is_dead());
+    let hit = world
+        .events()
+        .drain()
+        .into_iter()
+        .find_map(|event| match event.kind {
+            EventKind::ShipCollided { asteroid, .. } => Some(asteroid),
+            _ => None,
+        });
+    assert_eq!(hit, Some(planet));
+}
+
+#[test]
+fn test_spin_keeps_angular_momentum_through_merges_and_ship_contacts() {
+    // Two bodies passing each other off-centre merge into one spinning body
+    let mut a = Asteroid::new(vec2(0.0, 0.0), vec2(0.0, 2.0), 100.0);
+    let b = Asteroid::new(vec2(5.0, 0.0), vec2(0.0, -1.0), 50.0);
+    a.set_spin(0.5);
+    let merged = a.merge_with(&b);
+    let center = merged.pos();
+    let before = a.spin_angular_momentum()
+        + b.spin_angular_momentum()
+        + a.size() * (a.pos() - center).perp_dot(a.vel() - merged.vel())
+        + b.size() * (b.pos() - center).perp_dot(b.vel() - merged.vel());
+    assert!((merged.spin_angular_momentum() - before).abs() < 1e-3 * before.abs());
+    // Clockwise orbit of the pair outweighs the counter-clockwise spin of `a`
+    assert!(merged.spin() < 0.0);
+    assert_eq!(
+        merged.moment_of_inertia(),
+        0.5 * merged.size() * merged.radius() * merged.radius()
+    );
+
+    // Merges in a world keep the total, spin included
+    let mut world = WorldState::with_seed(8);
+    world.ship.pos = vec2(1.0e12, 0.0);
+    world.set_boundary(Boundary::Open);
+    world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 2.0), 100.0);
+    world.spawn_asteroid(vec2(5.0, 0.0), vec2(0.0, -1.0), 50.0);
+    let before = world.conservation().angular_momentum;
+    world.update(0.05);
+    assert_eq!(world.asteroids.len(), 1);
+    assert!(world.asteroids[0].spin() != 0.0);
+    let after = world.conservation().angular_momentum;
+    assert!((after - before).abs() < 1e-3 * before.abs());
+
+    // The ship grazing the top of an asteroid drags its surface along and spins it clockwise
+    let mut world = WorldState::with_seed(8);
+    world.set_boundary(Boundary::Open);
+    world.set_gravity(Gravity {
+        constant: 0.0,
+        ..Gravity::default()
+    });
+    let rock = world.spawn_asteroid(vec2(0.0, 0.0), vec2(0.0, 0.0), 400.0);
+    let radius = world.asteroid(rock).unwrap().radius();
+    world.ship.pos = vec2(0.0, radius + world.ship.radius() - 0.5);
+    world.ship.vel = vec2(50.0, 0.0);
+    world.update(1.5 / world.tick_rate());
+    let rock = *world.asteroid(rock).unwrap();
+    assert!(rock.spin() < 0.0);
+    assert!(rock.vel().x > 0.0);
+    assert!(world.ship.vel.x < 50.0);
+
+    // Spinning bodies turn as time passes
+    let angle = rock.angle();
+    world.update(0.5);
+    assert_ne!(world.asteroids[0].angle(), angle);
+}
+
+#[test]
+fn test_materials_set_density_and_mix_on_merge() {
+    // Rock keeps the radius every body used to have, ice is larger and metal smaller
+    let rock = Asteroid::new(vec2(0.0, 0.0), vec2(0.0, 0.0), 100.0);
+    assert_eq!(rock.composition(), Composition::pure(Material::Rock));
+    assert!((rock.radius() - 100.0f32.sqrt() / std::f32::consts::PI).abs() < 1e-5);
+    let ice = rock.with_composition(Material::Ice);
+    let metal = rock.with_composition(Material::Metal);
+    assert!(ice.radius() > rock.radius());
+    assert!(metal.radius() < rock.radius());
+    for body in [rock, ice, metal] {
+        let area = std::f32::consts::PI * body.radius() * body.radius();
+        assert!((body.size() / area - body.density()).abs() < 1e-3 * body.density());
+    }
+
+    // A merge mixes the materials by mass, with a density in between
+    let ice = Asteroid::new(vec2(0.0, 0.0), vec2(0.0, 0.0), 300.0).with_composition(Material::Ice);
+    let metal =
+        Asteroid::new(vec2(5.0, 0.0), vec2(0.0, 0.0), 100.0).with_composition(Material::Metal);
+    let merged = ice.merge_with(&metal);
+    let composition = merged.composition();
+    assert!((composition.fraction(Material::Ice) - 0.75).abs() < 1e-6);
+    assert!((composition.fraction(Material::Metal) - 0.25).abs() < 1e-6);
+    assert_eq!(composition.fraction(Material::Rock), 0.0);
+    assert_eq!(composition.dominant(), Material::Ice);
+    assert!(merged.density() > ice.density() && merged.density() < metal.density());
+    // Each material keeps its own area
+    let area = |body: &Asteroid| body.radius() * body.radius();
+    assert!((area(&merged) - area(&ice) - area(&metal)).abs() < 1e-3 * area(&merged));
+
+    // Fragments are made of the pair's mixed material
+    let mut rng = fastrand::Rng::with_seed(3);
+    let fragments = Fragmentation::default().fragment(&ice, &metal, &mut rng);
+    assert!(fragments.len() > 1);
+    for fragment in &fragments {
+        assert_eq!(fragment.composition(), composition);
+    }
+
+    // Hand-placed bodies are made of the chosen material
+    let mut world = WorldState::with_seed(4);
+    let mut strategy: Box<dyn SpawnStrategy> = Box::new(OrbitalDiskStrategy::new());
+    Input::PlaceAsteroid {
+        pos: vec2(0.0, 0.0),
+        vel: vec2(0.0, 0.0),
+        size: 50.0,
+        material: Material::Metal,
+    }
+    .apply(&mut world, &mut strategy);
+    assert_eq!(
+        world.asteroids[0].composition(),
+        Composition::pure(Material::Metal)
+    );
+
+    // Orbital disks have metal bodies inside and ice further out
+    let mut world = WorldState::with_seed(6);
+    let mut strategy = OrbitalDiskStrategy::new();
+    for _ in 0..300 {
+        strategy.spawn(&mut world, &SpawnView::default());
+    }
+    let mean_distance = |material: Material| {
+        let bodies: Vec<f32> = world
+            .asteroids
+            .iter()
+            .filter(|a| a.composition() == Composition::pure(material))
+            .map(|a| a.pos().length())
+            .collect();
+        assert!(!bodies.is_empty(), "No {} bodies", material.name());
+        bodies.iter().sum::<f32>() / bodies.len() as f32
+    };
+    assert!(mean_distance(Material::Metal) < mean_distance(Material::Rock));
+    assert!(mean_distance(Material::Rock) < mean_distance(Material::Ice));
+
+    // Solar systems get metal-cored planets and icy moons around a rocky star
+    let mut world = WorldState::with_seed(7);
+    let mut strategy = SolarSystemStrategy::new();
+    for _ in 0..100 {
+        strategy.spawn(&mut world, &SpawnView::default());
+    }
+    let mut sizes: Vec<f32> = world.asteroids.iter().map(|a| a.size()).collect();
+    sizes.sort_by(f32::total_cmp);
+    sizes.dedup();
+    assert_eq!(sizes.len(), 3, "Moons, planets and the star");
+    let made_of = |size: f32| {
+        let bodies = world.asteroids.iter().filter(move |a| a.size() == size);
+        bodies.map(|a| a.composition()).collect::<Vec<_>>()
+    };
+    assert!(
+        made_of(sizes[0])
+            .iter()
+            .all(|c| c.dominant() == Material::Ice)
+    );
+    assert!(
+        made_of(sizes[1])
+            .iter()
+            .all(|c| c.fraction(Material::Metal) > 0.0 && c.dominant() == Material::Rock)
+    );
+    assert_eq!(made_of(sizes[2]), vec![Composition::pure(Material::Rock)]);
+}